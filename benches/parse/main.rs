@@ -0,0 +1,74 @@
+//! Benchmarks for parsing large inputs into a `Value`, comparing the hand-written parser in
+//! `src/parser.rs` with the nom parser it replaced, which is kept in `nom_baseline.rs`.
+//!
+//! Run them with `cargo bench`; each input is parsed by both, in the `*_nom` benchmarks by the
+//! baseline.
+
+mod nom_baseline;
+
+use bencher::{benchmark_group, benchmark_main, black_box, Bencher};
+use serde_sexpr::Value;
+
+/// Returns a single list of 100,000 atoms: 25,000 each of symbols, escaped symbols, strings and
+/// integers.
+fn wide_input() -> String {
+    let mut s = String::from("(");
+    for i in 0..25_000 {
+        s.push_str(&format!(
+            "sym-{} |escaped\\ sym\\|{}| \"string\\t{}\\u{{1f600}}\" {} ",
+            i,
+            i,
+            i,
+            i * 7
+        ));
+    }
+    s.push(')');
+    s
+}
+
+/// Returns a list of 10,000 records, each of which is a few nested lists with comments.
+fn records_input() -> String {
+    let mut s = String::from("(\n");
+    for i in 0..10_000 {
+        s.push_str(&format!(
+            "  ((name \"record {}\") ; a record\n   (id . {})\n   (tags [a b c])\n   \
+             (nested (((x) y) z)) #;(skipped datum) 'quoted)\n",
+            i, i
+        ));
+    }
+    s.push(')');
+    s
+}
+
+fn parse_wide(b: &mut Bencher) {
+    let s = wide_input();
+    b.bytes = s.len() as u64;
+    b.iter(|| black_box(&s).parse::<Value>().unwrap());
+}
+
+fn parse_records(b: &mut Bencher) {
+    let s = records_input();
+    b.bytes = s.len() as u64;
+    b.iter(|| black_box(&s).parse::<Value>().unwrap());
+}
+
+fn parse_wide_nom(b: &mut Bencher) {
+    let s = wide_input();
+    b.bytes = s.len() as u64;
+    b.iter(|| nom_baseline::parse(black_box(&s)).unwrap());
+}
+
+fn parse_records_nom(b: &mut Bencher) {
+    let s = records_input();
+    b.bytes = s.len() as u64;
+    b.iter(|| nom_baseline::parse(black_box(&s)).unwrap());
+}
+
+benchmark_group!(
+    benches,
+    parse_wide,
+    parse_wide_nom,
+    parse_records,
+    parse_records_nom
+);
+benchmark_main!(benches);
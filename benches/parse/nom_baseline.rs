@@ -0,0 +1,245 @@
+//! The nom parser that `src/parser.rs` replaced, kept as a baseline for the benchmarks.
+//!
+//! This is the parser as of that change, cut down to the parts that matter for speed: it parses
+//! the same syntax, but reports every error as `None`, does not check `Limits`, and always parses
+//! bracketed lists as a `Value::List`. Bare atoms are parsed to a `Value::Sym` and quoted ones to
+//! a `Value::Str`, without the check for integers, floats, booleans and keywords that the current
+//! parser does, so the baseline does slightly less work than the parser it is compared with.
+
+use nom::{
+    alt_complete, anychar, char, delimited, do_parse, many0, map, named, preceded, return_error,
+    tag, take_while, take_while1, types::CompleteStr, value, Context, Err, ErrorKind, IResult,
+};
+use serde_sexpr::Value;
+use std::{borrow::Cow, str::Chars};
+
+/// Parses a value, returning `None` if the input is not a single value.
+pub fn parse(s: &str) -> Option<Value> {
+    match parser(CompleteStr(s)) {
+        Ok((CompleteStr(""), value)) => Some(value),
+        _ => None,
+    }
+}
+
+named!(parser<CompleteStr, Value>, delimited!(ws, value, ws));
+
+named!(value<CompleteStr, Value>, alt_complete!(
+    list | quoted | string | escaped_sym | dot | unescaped_sym));
+
+/// Parses a list or dotted list.
+fn list(input: CompleteStr) -> IResult<CompleteStr, Value> {
+    let close = match input.chars().next().and_then(closing_bracket) {
+        Some(close) => close,
+        None => return Err(Err::Error(Context::Code(input, ErrorKind::Char))),
+    };
+    let mut rest = CompleteStr(&input[1..]);
+    let mut vs = Vec::new();
+    loop {
+        rest = ws(rest)?.0;
+        if rest.starts_with(close) {
+            return Ok((CompleteStr(&rest[1..]), Value::List(vs)));
+        } else if !vs.is_empty() && is_dot(rest.0) {
+            let (rest, tail) = value(ws(CompleteStr(&rest[1..]))?.0).map_err(failure)?;
+            let rest = ws(rest)?.0;
+            return if rest.starts_with(close) {
+                Ok((CompleteStr(&rest[1..]), dotted_list(vs, tail)))
+            } else {
+                fail(&rest)
+            };
+        }
+        let (r, v) = value(rest).map_err(failure)?;
+        rest = r;
+        vs.push(v);
+    }
+}
+
+/// Parses a quote shorthand, such as `'x`.
+fn quoted(input: CompleteStr) -> IResult<CompleteStr, Value> {
+    do_parse!(
+        input,
+        sym: quote_prefix
+            >> ws
+            >> v: return_error!(value)
+            >> (Value::List(vec![Value::Sym(sym.to_string()), v]))
+    )
+}
+
+named!(quote_prefix<CompleteStr, &'static str>, alt_complete!(
+    value!("quote", char!('\'')) |
+    value!("quasiquote", char!('`')) |
+    value!("unquote-splicing", tag!(",@")) |
+    value!("unquote", char!(','))));
+
+/// Fails on a lone `.`, which is only allowed before the tail of a dotted list.
+fn dot(input: CompleteStr) -> IResult<CompleteStr, Value> {
+    if is_dot(input.0) {
+        fail(&input)
+    } else {
+        Err(Err::Error(Context::Code(input, ErrorKind::Char)))
+    }
+}
+
+named!(escaped_sym<CompleteStr, Value>, do_parse!(
+    char!('|') >>
+    s: many0!(sym_chs) >>
+    return_error!(char!('|')) >>
+    (Value::Str(s.into_iter().collect()))));
+named!(unescaped_sym<CompleteStr, Value>,
+    map!(take_while1!(doesnt_need_quoting),
+         |s| Value::Sym(s.to_string())));
+
+named!(sym_chs<CompleteStr, Cow<str>>, alt_complete!(escaped_ch | unescaped_chs));
+named!(escaped_ch<CompleteStr, Cow<str>>, do_parse!(
+    char!('\\') >>
+    ch: return_error!(anychar) >>
+    (Cow::Owned(Some(ch).into_iter().collect()))));
+named!(unescaped_chs<CompleteStr, Cow<str>>,
+    map!(take_while1!(doesnt_need_quoting), |CompleteStr(s)| Cow::Borrowed(s)));
+
+/// Parses a string literal.
+fn string(input: CompleteStr) -> IResult<CompleteStr, Value> {
+    let mut rest = match input.0.chars().next() {
+        Some('"') => &input.0[1..],
+        _ => return Err(Err::Error(Context::Code(input, ErrorKind::Char))),
+    };
+    let mut s = String::new();
+    loop {
+        let mut chars = rest.chars();
+        match chars.next() {
+            None => return fail(rest),
+            Some('"') => return Ok((CompleteStr(chars.as_str()), Value::Str(s))),
+            Some('\\') => match chars.next().and_then(|ch| unescape(ch, &mut chars)) {
+                Some(ch) => s.push(ch),
+                None => return fail(rest),
+            },
+            Some(ch) => s.push(ch),
+        }
+        rest = chars.as_str();
+    }
+}
+
+/// Fails unrecoverably at `rest`.
+fn fail<T>(rest: &str) -> IResult<CompleteStr<'_>, T> {
+    Err(Err::Failure(Context::Code(
+        CompleteStr(rest),
+        ErrorKind::Custom(0),
+    )))
+}
+
+/// Turns a recoverable error into an unrecoverable one, for a value that must be present.
+fn failure(err: Err<CompleteStr>) -> Err<CompleteStr> {
+    match err {
+        Err::Error(context) => Err::Failure(context),
+        err => err,
+    }
+}
+
+/// Decodes the escape sequence in a string literal that starts with the given character, after
+/// the backslash. Any further characters of the sequence are taken from `chars`.
+fn unescape(ch: char, chars: &mut Chars) -> Option<char> {
+    match ch {
+        '"' | '\\' => Some(ch),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        '0' => Some('\0'),
+        'u' => {
+            if chars.next() != Some('{') {
+                return None;
+            }
+            let digits = chars.as_str().split('}').next()?;
+            let valid =
+                !digits.is_empty() && digits.len() <= 6 && digits.len() < chars.as_str().len();
+            if !valid || !digits.chars().all(|ch| ch.is_ascii_hexdigit()) {
+                return None;
+            }
+            let code = u32::from_str_radix(digits, 16).ok()?;
+            *chars = chars.as_str()[digits.len() + 1..].chars();
+            char::from_u32(code)
+        }
+        _ => None,
+    }
+}
+
+named!(ws<CompleteStr, ()>, map!(
+    many0!(alt_complete!(whitespace | line_comment | block_comment | datum_comment)),
+    |_| ()));
+
+named!(whitespace<CompleteStr, ()>, map!(take_while1!(char::is_whitespace), |_| ()));
+named!(line_comment<CompleteStr, ()>,
+    map!(preceded!(char!(';'), take_while!(is_not_newline)), |_| ()));
+named!(block_comment<CompleteStr, ()>, do_parse!(
+    tag!("#|") >>
+    return_error!(block_comment_rest) >>
+    ()));
+named!(datum_comment<CompleteStr, ()>, do_parse!(
+    tag!("#;") >>
+    ws >>
+    return_error!(value) >>
+    ()));
+
+/// Parses the remainder of a block comment after its opening `#|`, including any nested block
+/// comments.
+fn block_comment_rest(input: CompleteStr) -> IResult<CompleteStr, ()> {
+    let mut depth = 1;
+    let mut rest = input.0;
+    while depth > 0 {
+        if rest.starts_with("|#") {
+            depth -= 1;
+            rest = &rest[2..];
+        } else if rest.starts_with("#|") {
+            depth += 1;
+            rest = &rest[2..];
+        } else if let Some(ch) = rest.chars().next() {
+            rest = &rest[ch.len_utf8()..];
+        } else {
+            return Err(Err::Error(Context::Code(input, ErrorKind::Custom(0))));
+        }
+    }
+    Ok((CompleteStr(rest), ()))
+}
+
+/// Creates a dotted list with the given elements and tail, merging a list or dotted list tail
+/// into the elements.
+fn dotted_list(mut vs: Vec<Value>, mut tail: Value) -> Value {
+    match &mut tail {
+        Value::List(tail) => {
+            vs.append(tail);
+            Value::List(vs)
+        }
+        Value::DottedList(tail, last) => {
+            vs.append(tail);
+            let last = std::mem::replace(&mut **last, Value::List(Vec::new()));
+            Value::DottedList(vs, Box::new(last))
+        }
+        _ => Value::DottedList(vs, Box::new(tail)),
+    }
+}
+
+/// Returns whether `s` starts with a lone `.`, rather than a symbol that starts with `.`.
+fn is_dot(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next() == Some('.') && chars.next().map_or(true, needs_quoting)
+}
+
+/// Returns the closing bracket that matches the given opening bracket, if it is one.
+fn closing_bracket(open: char) -> Option<char> {
+    match open {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        _ => None,
+    }
+}
+
+fn needs_quoting(ch: char) -> bool {
+    ch.is_whitespace() || "\"()[]{};|\\".contains(ch)
+}
+
+fn doesnt_need_quoting(ch: char) -> bool {
+    !needs_quoting(ch)
+}
+
+fn is_not_newline(ch: char) -> bool {
+    ch != '\n'
+}
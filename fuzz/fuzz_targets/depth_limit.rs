@@ -0,0 +1,40 @@
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+
+use serde::{Deserialize, Serialize};
+use serde_sexpr::{from_reader, from_str, from_value, to_string, Value};
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum Tree {
+    Leaf(String),
+    Node(Vec<Tree>),
+}
+
+// Nests the input inside up to 255,000 lists, and checks that parsing, deserializing and
+// serializing it returns rather than overflowing the stack.
+fuzz_target!(|data: &[u8]| {
+    let (&depth, data) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let body = match std::str::from_utf8(data) {
+        Ok(body) => body,
+        Err(_) => return,
+    };
+    let depth = depth as usize * 1000;
+    let s = format!("{}{}{}", "(".repeat(depth), body, ")".repeat(depth));
+
+    if let Ok(value) = s.parse::<Value>() {
+        let _ = value.to_string();
+        if let Ok(tree) = from_value::<Tree>(value) {
+            let _ = to_string(&tree);
+        }
+    }
+    if let Ok(tree) = from_str::<Tree>(&s) {
+        let _ = to_string(&tree);
+    }
+    let _ = from_reader::<_, Tree>(s.as_bytes());
+});
@@ -1,12 +1,26 @@
-use crate::{Error, Result, Value};
-use serde::de::{DeserializeOwned, DeserializeSeed, Deserializer, Visitor};
+use crate::{
+    closing_bracket,
+    lexer::{lex_escaped_symbol, lex_quote, lex_string, lex_trivia, peek_kind, TokenKind},
+    needs_quoting,
+    read::{IoRead, Read, Reference, StrRead},
+    Error, Limit, Limits, OptionEncoding, Position, Result, Span, SyntaxError, Value,
+    DEFAULT_MAX_DEPTH,
+};
+use serde::de::{Deserialize, DeserializeOwned, DeserializeSeed, Error as _, Unexpected, Visitor};
 use std::{
-    io::Read,
+    convert::TryFrom,
+    io::BufRead,
+    marker::PhantomData,
+    mem::take,
     str::{from_utf8, FromStr},
 };
 
 /// Deserialize an instance of `T` from an S-Expression in a reader.
 ///
+/// The input is parsed incrementally as the deserializer asks for data, so only the current atom
+/// and a small amount of state per level of nesting is kept in memory. A reader that is not
+/// buffered, such as a `File`, should be wrapped in a `std::io::BufReader`.
+///
 /// # Examples
 ///
 /// ```
@@ -15,10 +29,36 @@ use std::{
 /// let value: Vec<String> = serde_sexpr::from_reader(c).unwrap();
 /// assert_eq!(value, vec!["Hello!".to_string(), "Goodbye, world!".to_string(), ")|(".to_string()]);
 /// ```
-pub fn from_reader<R: Read, T: DeserializeOwned>(mut reader: R) -> Result<T> {
-    let mut buf = Vec::new();
-    let _ = reader.read_to_end(&mut buf)?;
-    from_slice(&buf)
+pub fn from_reader<R: BufRead, T: DeserializeOwned>(reader: R) -> Result<T> {
+    from_reader_with(reader, Limits::default())
+}
+
+/// Deserialize an instance of `T` from an S-Expression in a reader, within the given limits.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_sexpr::{Error, Limit, Limits};
+/// let limits = Limits {
+///     max_input_len: 16,
+///     ..Limits::default()
+/// };
+/// let c = "(Hello, world!)".as_bytes();
+/// let value: Vec<String> = serde_sexpr::from_reader_with(c, limits).unwrap();
+/// assert_eq!(value, vec!["Hello,", "world!"]);
+///
+/// let c = "(Goodbye, world!)".as_bytes();
+/// match serde_sexpr::from_reader_with::<_, Vec<String>>(c, limits) {
+///     Err(Error::LimitExceeded(Limit::InputLength(16), _)) => {}
+///     r => panic!("{:?}", r),
+/// }
+/// ```
+pub fn from_reader_with<R: BufRead, T: DeserializeOwned>(reader: R, limits: Limits) -> Result<T> {
+    let mut de = Deserializer::from_reader(reader);
+    de.set_limits(limits);
+    let value = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(value)
 }
 
 /// Deserialize an instance of `T` from an S-Expression in a slice.
@@ -30,8 +70,13 @@ pub fn from_reader<R: Read, T: DeserializeOwned>(mut reader: R) -> Result<T> {
 /// let value: Vec<String> = serde_sexpr::from_slice(s).unwrap();
 /// assert_eq!(value, vec!["Hello!".to_string(), "Goodbye, world!".to_string(), ")|(".to_string()]);
 /// ```
-pub fn from_slice<T: DeserializeOwned>(slice: &[u8]) -> Result<T> {
-    from_str(from_utf8(slice)?)
+pub fn from_slice<'de, T: Deserialize<'de>>(slice: &'de [u8]) -> Result<T> {
+    from_slice_with(slice, Limits::default())
+}
+
+/// Deserialize an instance of `T` from an S-Expression in a slice, within the given limits.
+pub fn from_slice_with<'de, T: Deserialize<'de>>(slice: &'de [u8], limits: Limits) -> Result<T> {
+    from_str_with(from_utf8(slice)?, limits)
 }
 
 /// Deserialize an instance of `T` from an S-Expression in a string.
@@ -43,127 +88,396 @@ pub fn from_slice<T: DeserializeOwned>(slice: &[u8]) -> Result<T> {
 /// let value: Vec<String> = serde_sexpr::from_str(s).unwrap();
 /// assert_eq!(value, vec!["Hello!".to_string(), "Goodbye, world!".to_string(), ")|(".to_string()]);
 /// ```
-pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T> {
-    let value = s.parse::<Value>()?;
-    from_value(value)
+///
+/// Unescaped symbols can be borrowed from the input. Escaped symbols have to be copied, so they
+/// can only be deserialized as owned strings or as a `Cow`.
+///
+/// ```
+/// # use std::borrow::Cow;
+/// let value: (&str, Cow<str>) = serde_sexpr::from_str("(foo |bar\\ baz|)").unwrap();
+/// assert_eq!(value, ("foo", Cow::Owned("bar baz".to_string())));
+///
+/// assert!(serde_sexpr::from_str::<&str>("|bar\\ baz|").is_err());
+/// ```
+pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T> {
+    from_str_with(s, Limits::default())
+}
+
+/// Deserialize an instance of `T` from an S-Expression in a string, within the given limits.
+pub fn from_str_with<'de, T: Deserialize<'de>>(s: &'de str, limits: Limits) -> Result<T> {
+    let mut de = Deserializer::from_str(s);
+    de.set_limits(limits);
+    let value = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(value)
 }
 
 /// Interpret a `serde_sexpr::Value` as an instance of type `T`.
+///
+/// Returns an error if the value has lists nested more than `DEFAULT_MAX_DEPTH` deep.
 pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T> {
-    T::deserialize(value)
+    from_value_with(value, Limits::default())
+}
+
+/// Interpret a `serde_sexpr::Value` as an instance of type `T`, returning an error if it exceeds
+/// the given limits. `Limits::max_input_len` is ignored.
+pub fn from_value_with<T: DeserializeOwned>(value: Value, limits: Limits) -> Result<T> {
+    value.check_limits(limits)?;
+    let mut de = ValueDeserializer::new(value);
+    de.set_max_depth(limits.max_depth);
+    T::deserialize(de)
+}
+
+/// A deserializer for a `Value`, with settings that change how it is deserialized.
+///
+/// `Value` implements `serde::Deserializer` itself, with the default settings. As when parsing,
+/// deserializing a value with lists nested more than `DEFAULT_MAX_DEPTH` deep returns an error
+/// rather than overflowing the stack.
+///
+/// # Examples
+///
+/// ```
+/// # use serde::Deserialize;
+/// # use serde_sexpr::{sexpr, ValueDeserializer};
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// #[serde(untagged)]
+/// enum Setting {
+///     Number(u32),
+///     Text(String),
+/// }
+///
+/// let value = sexpr!((42 "forty-two"));
+/// let settings = Vec::<Setting>::deserialize(ValueDeserializer::new(value.clone())).unwrap();
+/// assert_eq!(settings, [Setting::Number(42), Setting::Text("forty-two".to_string())]);
+///
+/// let mut de = ValueDeserializer::new(value);
+/// de.set_infer_types(false);
+/// let settings = Vec::<Setting>::deserialize(de).unwrap();
+/// assert_eq!(settings, [Setting::Text("42".to_string()), Setting::Text("forty-two".to_string())]);
+/// ```
+#[derive(Debug)]
+pub struct ValueDeserializer {
+    value: Value,
+    context: Context,
+}
+
+impl ValueDeserializer {
+    /// Creates a deserializer for the given value, with the default settings.
+    pub fn new(value: Value) -> ValueDeserializer {
+        let context = Context {
+            settings: Settings::default(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+        };
+        context.deserializer(value)
+    }
+
+    /// Sets how deeply lists in the value may be nested, which is `DEFAULT_MAX_DEPTH` by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde::Deserialize;
+    /// # use serde_sexpr::{sexpr, Error, Limit, ValueDeserializer};
+    /// let mut de = ValueDeserializer::new(sexpr!(((1) (2 3))));
+    /// de.set_max_depth(2);
+    /// assert!(Vec::<Vec<u32>>::deserialize(de).is_ok());
+    ///
+    /// let mut de = ValueDeserializer::new(sexpr!((((1)))));
+    /// de.set_max_depth(2);
+    /// match Vec::<Vec<Vec<u32>>>::deserialize(de) {
+    ///     Err(Error::LimitExceeded(Limit::Depth(2), None)) => {}
+    ///     r => panic!("{:?}", r),
+    /// }
+    /// ```
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.context.max_depth = max_depth;
+    }
+
+    /// Sets whether `deserialize_any` infers the types of atoms, as it does by default.
+    ///
+    /// When it does, integers, floats and booleans are deserialized as such, and the empty list
+    /// `()` is deserialized as a unit, which can also stand for `None`. Otherwise, every atom is
+    /// deserialized as a string and every list as a sequence.
+    pub fn set_infer_types(&mut self, infer_types: bool) {
+        self.context.settings.infer_types = infer_types;
+    }
+
+    /// Sets whether `deserialize_any` deserializes association lists, such as `((a 1) (b 2))`, as
+    /// maps. It does not by default.
+    ///
+    /// A non-empty list is an association list if each of its elements is a two-element list or
+    /// a dotted pair whose first element is an atom. This is needed for `#[serde(flatten)]` and
+    /// internally or adjacently tagged enums, but means that such a list cannot be deserialized
+    /// as a sequence by `deserialize_any`, such as a `Vec<(String, i32)>` in an untagged enum.
+    pub fn set_infer_maps(&mut self, infer_maps: bool) {
+        self.context.settings.infer_maps = infer_maps;
+    }
+
+    /// Sets how `Option`s are expected to be written, which is `OptionEncoding::Transparent` by
+    /// default.
+    pub fn set_option_encoding(&mut self, option_encoding: OptionEncoding) {
+        self.context.settings.option_encoding = option_encoding;
+    }
+
+    /// Returns the context for deserializing the elements of the value, or an error if it is a
+    /// list that is nested too deeply.
+    fn elements(&self) -> Result<Context> {
+        let context = self.context;
+        if context.depth >= context.max_depth {
+            return Err(Error::LimitExceeded(Limit::Depth(context.max_depth), None));
+        }
+        Ok(Context {
+            depth: context.depth + 1,
+            ..context
+        })
+    }
+}
+
+/// The settings of a deserializer.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Settings {
+    /// Whether `deserialize_any` infers the types of atoms and treats `()` as a unit.
+    pub(crate) infer_types: bool,
+
+    /// Whether `deserialize_any` treats association lists as maps.
+    pub(crate) infer_maps: bool,
+
+    /// How `Option`s are written.
+    pub(crate) option_encoding: OptionEncoding,
+}
+
+/// The settings of a `ValueDeserializer`, and how deeply nested the value it deserializes is.
+#[derive(Clone, Copy, Debug)]
+struct Context {
+    settings: Settings,
+
+    /// The number of lists that the value is inside.
+    depth: usize,
+
+    /// The maximum number of lists that can be nested inside each other.
+    max_depth: usize,
+}
+
+impl Context {
+    /// Returns a deserializer for a value in this context, such as an element of a list.
+    fn deserializer(self, value: Value) -> ValueDeserializer {
+        ValueDeserializer {
+            value,
+            context: self,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            infer_types: true,
+            infer_maps: false,
+            option_encoding: OptionEncoding::default(),
+        }
+    }
+}
+
+/// Returns whether the elements of a list make it an association list, which `deserialize_any`
+/// deserializes as a map.
+fn is_alist(vs: &[Value]) -> bool {
+    let is_pair = |v: &Value| match v {
+        Value::List(pair) | Value::Bracketed(_, pair) => pair.len() == 2 && pair[0].is_atom(),
+        Value::DottedList(pair, _) => pair.len() == 1 && pair[0].is_atom(),
+        _ => false,
+    };
+    !vs.is_empty() && vs.iter().all(is_pair)
+}
+
+/// Visits an integer with the narrowest of `visit_u64`, `visit_i64` and `visit_i128` that fits it.
+fn visit_int<'de, V: Visitor<'de>>(n: i128, visitor: V) -> Result<V::Value> {
+    if let Ok(n) = u64::try_from(n) {
+        visitor.visit_u64(n)
+    } else if let Ok(n) = i64::try_from(n) {
+        visitor.visit_i64(n)
+    } else {
+        visitor.visit_i128(n)
+    }
 }
 
 impl Value {
-    /// Deserializes a `FromStr` from a `Sym`.
+    /// Deserializes a `FromStr`, such as a number, from an atom other than a `Str`.
     fn deserialize_generic<T: FromStr>(self, name: &'static str) -> Result<T> {
-        match &self {
-            Value::List(_) => Err(Error::Invalid(name, self)),
-            Value::Sym(s) => s.parse().map_err(|_| Error::Invalid(name, self)),
-        }
+        self.deserialize_parsed(name, |s| s.parse().ok())
+    }
+
+    /// Deserializes an atom other than a `Str` with the given parsing function, which is given
+    /// the atom's text.
+    fn deserialize_parsed<T>(
+        self,
+        name: &'static str,
+        parse: impl FnOnce(&str) -> Option<T>,
+    ) -> Result<T> {
+        let parsed = match &self {
+            Value::Str(_) => None,
+            value => value.atom_text().and_then(|s| parse(&s)),
+        };
+        parsed.ok_or(Error::Invalid(name, self))
+    }
+
+    /// Deserializes a `FromStr`, such as a string, from any atom.
+    fn deserialize_text<T: FromStr>(self, name: &'static str) -> Result<T> {
+        let parsed = self.atom_text().and_then(|s| s.parse().ok());
+        parsed.ok_or(Error::Invalid(name, self))
     }
 }
 
-impl<'de> Deserializer<'de> for Value {
+impl<'de> serde::de::Deserializer<'de> for ValueDeserializer {
     type Error = Error;
 
     fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        match &self {
-            Value::List(_) => self.deserialize_seq(visitor),
-            Value::Sym(_) => self.deserialize_str(visitor),
+        match &self.value {
+            _ if !self.context.settings.infer_types => {}
+            Value::Int(n) => return visit_int(*n, visitor),
+            Value::Float(f) => return visitor.visit_f64(*f),
+            Value::Bool(b) => return visitor.visit_bool(*b),
+            Value::List(vs) | Value::Bracketed(_, vs) if vs.is_empty() => {
+                return visitor.visit_unit();
+            }
+            _ => {}
+        }
+        match &self.value {
+            Value::List(vs) | Value::Bracketed(_, vs)
+                if self.context.settings.infer_maps && is_alist(vs) =>
+            {
+                return self.deserialize_map(visitor);
+            }
+            _ => {}
+        }
+        if self.value.is_atom() {
+            self.deserialize_str(visitor)
+        } else {
+            self.deserialize_seq(visitor)
         }
     }
 
     fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_bool(self.deserialize_generic("bool")?)
+        visitor.visit_bool(self.value.deserialize_generic("bool")?)
     }
 
     fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i8(self.deserialize_generic("i8")?)
+        visitor.visit_i8(self.value.deserialize_generic("i8")?)
     }
 
     fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i16(self.deserialize_generic("i16")?)
+        visitor.visit_i16(self.value.deserialize_generic("i16")?)
     }
 
     fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i32(self.deserialize_generic("i32")?)
+        visitor.visit_i32(self.value.deserialize_generic("i32")?)
     }
 
     fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i64(self.deserialize_generic("i64")?)
+        visitor.visit_i64(self.value.deserialize_generic("i64")?)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i128(self.value.deserialize_generic("i128")?)
     }
 
     fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u8(self.deserialize_generic("u8")?)
+        visitor.visit_u8(self.value.deserialize_generic("u8")?)
     }
 
     fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u16(self.deserialize_generic("u16")?)
+        visitor.visit_u16(self.value.deserialize_generic("u16")?)
     }
 
     fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u32(self.deserialize_generic("u32")?)
+        visitor.visit_u32(self.value.deserialize_generic("u32")?)
     }
 
     fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u64(self.deserialize_generic("u64")?)
+        visitor.visit_u64(self.value.deserialize_generic("u64")?)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u128(self.value.deserialize_generic("u128")?)
     }
 
     fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_f32(self.deserialize_generic("f32")?)
+        visitor.visit_f32(self.value.deserialize_parsed("f32", parse_float)?)
     }
 
     fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_f64(self.deserialize_generic("f64")?)
+        visitor.visit_f64(self.value.deserialize_parsed("f64", parse_float)?)
     }
 
     fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_char(self.deserialize_generic("char")?)
+        visitor.visit_char(self.value.deserialize_text("char")?)
     }
 
     fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_string(self.deserialize_generic("string")?)
+        visitor.visit_string(self.value.deserialize_text("string")?)
     }
 
     fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_string(self.deserialize_generic("string")?)
+        visitor.visit_string(self.value.deserialize_text("string")?)
     }
 
     fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_generic("string")
+        self.value
+            .deserialize_text("string")
             .map(String::into_bytes)
             .and_then(|bs| visitor.visit_byte_buf(bs))
     }
 
     fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_generic("string")
+        self.value
+            .deserialize_text("string")
             .map(String::into_bytes)
             .and_then(|bs| visitor.visit_byte_buf(bs))
     }
 
-    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        match &self {
-            Value::List(l) => {
-                if l.is_empty() {
+    fn deserialize_option<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value> {
+        let option_encoding = self.context.settings.option_encoding;
+        match (option_encoding, &mut self.value) {
+            (OptionEncoding::Transparent, Value::List(vs))
+            | (OptionEncoding::Transparent, Value::Bracketed(_, vs)) => {
+                if vs.is_empty() {
                     visitor.visit_none()
                 } else {
                     visitor.visit_some(self)
                 }
             }
-            Value::Sym(_) => visitor.visit_some(self),
+            (OptionEncoding::Transparent, _) => visitor.visit_some(self),
+            (OptionEncoding::Wrapped, Value::List(vs))
+            | (OptionEncoding::Wrapped, Value::Bracketed(_, vs)) => match vs.len() {
+                0 => visitor.visit_none(),
+                1 => {
+                    let value = vs.pop().unwrap();
+                    visitor.visit_some(self.elements()?.deserializer(value))
+                }
+                _ => Err(Error::Invalid("option", self.value)),
+            },
+            (OptionEncoding::Tagged, Value::Sym(s)) if s == "none" => visitor.visit_none(),
+            (OptionEncoding::Tagged, Value::List(vs))
+            | (OptionEncoding::Tagged, Value::Bracketed(_, vs))
+                if vs.len() == 2 && vs[0] == Value::Sym("some".to_string()) =>
+            {
+                let value = vs.pop().unwrap();
+                visitor.visit_some(self.elements()?.deserializer(value))
+            }
+            _ => Err(Error::Invalid("option", self.value)),
         }
     }
 
     fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        match &self {
-            Value::List(l) => {
+        match &self.value {
+            Value::List(l) | Value::Bracketed(_, l) => {
                 if l.is_empty() {
                     visitor.visit_unit()
                 } else {
-                    Err(Error::Invalid("unit", self))
+                    Err(Error::Invalid("unit", self.value))
                 }
             }
-            Value::Sym(_) => Err(Error::Invalid("unit", self)),
+            _ => Err(Error::Invalid("unit", self.value)),
         }
     }
 
@@ -183,13 +497,15 @@ impl<'de> Deserializer<'de> for Value {
         visitor.visit_newtype_struct(self)
     }
 
-    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        match self {
-            Value::List(mut vs) => {
+    fn deserialize_seq<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value> {
+        let elements = self.elements();
+        match &mut self.value {
+            Value::List(vs) | Value::Bracketed(_, vs) => {
+                let mut vs = take(vs);
                 vs.reverse();
-                visitor.visit_seq(SeqAccess(vs))
+                visitor.visit_seq(SeqAccess(vs, elements?))
             }
-            Value::Sym(_) => Err(Error::Invalid("sequence", self)),
+            _ => Err(Error::Invalid("sequence", self.value)),
         }
     }
 
@@ -206,13 +522,15 @@ impl<'de> Deserializer<'de> for Value {
         self.deserialize_seq(visitor)
     }
 
-    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        match self {
-            Value::List(mut vs) => {
+    fn deserialize_map<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value> {
+        let elements = self.elements();
+        match &mut self.value {
+            Value::List(vs) | Value::Bracketed(_, vs) => {
+                let mut vs = take(vs);
                 vs.reverse();
-                visitor.visit_map(MapAccess(vs, None))
+                visitor.visit_map(MapAccess(vs, None, elements?))
             }
-            Value::Sym(_) => Err(Error::Invalid("map", self)),
+            _ => Err(Error::Invalid("map", self.value)),
         }
     }
 
@@ -239,50 +557,153 @@ impl<'de> Deserializer<'de> for Value {
     }
 
     fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_any(visitor)
+        visitor.visit_unit()
+    }
+}
+
+/// Implements a `serde::Deserializer` method for `Value` by deserializing it with a
+/// `ValueDeserializer` with the default settings.
+macro_rules! forward_to_value_deserializer {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                ValueDeserializer::new(self).$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> serde::de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    forward_to_value_deserializer! {
+        deserialize_any
+        deserialize_bool
+        deserialize_i8
+        deserialize_i16
+        deserialize_i32
+        deserialize_i64
+        deserialize_i128
+        deserialize_u8
+        deserialize_u16
+        deserialize_u32
+        deserialize_u64
+        deserialize_u128
+        deserialize_f32
+        deserialize_f64
+        deserialize_char
+        deserialize_str
+        deserialize_string
+        deserialize_bytes
+        deserialize_byte_buf
+        deserialize_option
+        deserialize_unit
+        deserialize_seq
+        deserialize_map
+        deserialize_identifier
+        deserialize_ignored_any
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        ValueDeserializer::new(self).deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        ValueDeserializer::new(self).deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        ValueDeserializer::new(self).deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        ValueDeserializer::new(self).deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        ValueDeserializer::new(self).deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        ValueDeserializer::new(self).deserialize_enum(name, variants, visitor)
     }
 }
 
-struct EnumAccess(Value);
+struct EnumAccess(ValueDeserializer);
 
 impl<'de> serde::de::EnumAccess<'de> for EnumAccess {
     type Error = Error;
     type Variant = VariantAccess;
 
     fn variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<(T::Value, Self::Variant)> {
-        match self.0 {
-            Value::List(mut vs) => {
+        let context = self.0.context;
+        let elements = self.0.elements();
+        let mut value = self.0.value;
+        match &mut value {
+            Value::List(vs) | Value::Bracketed(_, vs) => {
                 if vs.is_empty() {
                     Err(Error::Invalid("enum", Value::List(Vec::new())))
                 } else {
-                    seed.deserialize(vs.remove(0))
-                        .map(|v| (v, VariantAccess(Some(vs))))
+                    let elements = elements?;
+                    let mut vs = take(vs);
+                    seed.deserialize(elements.deserializer(vs.remove(0)))
+                        .map(|v| (v, VariantAccess(Some(vs), elements)))
                 }
             }
-            v => seed.deserialize(v).map(|v| (v, VariantAccess(None))),
+            _ => seed
+                .deserialize(context.deserializer(value))
+                .map(|v| (v, VariantAccess(None, context))),
         }
     }
 }
 
-struct MapAccess(Vec<Value>, Option<Value>);
+struct MapAccess(Vec<Value>, Option<ValueDeserializer>, Context);
 
 impl<'de> serde::de::MapAccess<'de> for MapAccess {
     type Error = Error;
 
     fn next_key_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
         debug_assert!(self.1.is_none());
-        match self.0.pop() {
-            Some(Value::List(mut vs)) => {
-                if vs.len() == 2 {
-                    self.1 = vs.pop();
-                    seed.deserialize(vs.pop().unwrap()).map(Some)
-                } else {
-                    Err(Error::Invalid("pair", Value::List(vs)))
-                }
+        let mut pair = match self.0.pop() {
+            Some(value) => self.2.deserializer(value),
+            None => return Ok(None),
+        };
+        // The key and value are elements of the pair, which is a list inside the map.
+        let elements = pair.elements();
+        let (key, value) = match &mut pair.value {
+            Value::List(vs) | Value::Bracketed(_, vs) if vs.len() == 2 => {
+                let value = vs.pop().unwrap();
+                (vs.pop().unwrap(), value)
             }
-            Some(v @ Value::Sym(_)) => Err(Error::Invalid("pair", v)),
-            None => Ok(None),
-        }
+            Value::DottedList(vs, tail) if vs.len() == 1 => (vs.pop().unwrap(), tail.take()),
+            _ => return Err(Error::Invalid("pair", pair.value)),
+        };
+        let elements = elements?;
+        self.1 = Some(elements.deserializer(value));
+        seed.deserialize(elements.deserializer(key)).map(Some)
     }
 
     fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value> {
@@ -291,7 +712,7 @@ impl<'de> serde::de::MapAccess<'de> for MapAccess {
     }
 }
 
-struct SeqAccess(Vec<Value>);
+struct SeqAccess(Vec<Value>, Context);
 
 impl<'de> serde::de::SeqAccess<'de> for SeqAccess {
     type Error = Error;
@@ -300,12 +721,13 @@ impl<'de> serde::de::SeqAccess<'de> for SeqAccess {
         if self.0.is_empty() {
             Ok(None)
         } else {
-            seed.deserialize(self.0.pop().unwrap()).map(Some)
+            seed.deserialize(self.1.deserializer(self.0.pop().unwrap()))
+                .map(Some)
         }
     }
 }
 
-struct VariantAccess(Option<Vec<Value>>);
+struct VariantAccess(Option<Vec<Value>>, Context);
 
 impl<'de> serde::de::VariantAccess<'de> for VariantAccess {
     type Error = Error;
@@ -319,13 +741,14 @@ impl<'de> serde::de::VariantAccess<'de> for VariantAccess {
         debug_assert!(self.0.is_some());
         debug_assert_eq!(self.0.as_ref().unwrap().len(), 1);
         let val = self.0.unwrap().pop().unwrap();
-        seed.deserialize(val)
+        seed.deserialize(self.1.deserializer(val))
     }
 
     fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
         debug_assert!(self.0.is_some());
-        let val = Value::List(self.0.unwrap());
-        val.deserialize_seq(visitor)
+        let mut vs = self.0.unwrap();
+        vs.reverse();
+        visitor.visit_seq(SeqAccess(vs, self.1))
     }
 
     fn struct_variant<V: Visitor<'de>>(
@@ -334,7 +757,1065 @@ impl<'de> serde::de::VariantAccess<'de> for VariantAccess {
         visitor: V,
     ) -> Result<V::Value> {
         debug_assert!(self.0.is_some());
-        let val = Value::List(self.0.unwrap());
-        val.deserialize_map(visitor)
+        let mut vs = self.0.unwrap();
+        vs.reverse();
+        visitor.visit_map(MapAccess(vs, None, self.1))
+    }
+}
+
+/// An iterator that deserializes a sequence of top-level S-Expressions.
+///
+/// Whitespace between the S-Expressions is skipped. If an S-Expression fails to parse, the error
+/// is returned and iteration stops. If it parses but cannot be deserialized as a `T`, the error is
+/// returned and iteration continues with the next S-Expression.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_sexpr::Deserializer;
+/// let mut stream = Deserializer::from_str("(foo 1) (bar 2)\n(baz 3)").into_iter::<(String, u32)>();
+/// assert_eq!(stream.next().unwrap().unwrap(), ("foo".to_string(), 1));
+/// assert_eq!(stream.byte_offset(), 7);
+/// assert_eq!(stream.next().unwrap().unwrap(), ("bar".to_string(), 2));
+/// assert_eq!(stream.next().unwrap().unwrap(), ("baz".to_string(), 3));
+/// assert!(stream.next().is_none());
+/// assert_eq!(stream.byte_offset(), 23);
+/// ```
+#[derive(Debug)]
+pub struct StreamDeserializer<'de, R, T> {
+    de: Deserializer<R>,
+    offset: usize,
+    failed: bool,
+    output: PhantomData<T>,
+    lifetime: PhantomData<&'de ()>,
+}
+
+impl<'de, R: Read<'de>, T> StreamDeserializer<'de, R, T> {
+    /// Creates a `StreamDeserializer` over the S-Expressions in the given source.
+    pub fn new(read: R) -> StreamDeserializer<'de, R, T> {
+        StreamDeserializer {
+            de: Deserializer::new(read),
+            offset: 0,
+            failed: false,
+            output: PhantomData,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Returns the byte offset just past the last S-Expression that was parsed.
+    ///
+    /// After a syntax error, this is the offset just past the last S-Expression that parsed
+    /// successfully, which is where the remaining input starts.
+    pub fn byte_offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'de, R: Read<'de>, T: Deserialize<'de>> Iterator for StreamDeserializer<'de, R, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.failed {
+            return None;
+        }
+
+        let r = match self.de.peek_token() {
+            Ok(None) => return None,
+            Ok(Some(_)) => T::deserialize(&mut self.de),
+            Err(err) => Err(err),
+        };
+        let r = match r {
+            Ok(value) => Ok(value),
+            Err(err @ Error::LimitExceeded(..))
+            | Err(err @ Error::Syntax(..))
+            | Err(err @ Error::Io(_))
+            | Err(err @ Error::Utf8(_)) => {
+                self.failed = true;
+                return Some(Err(err));
+            }
+            Err(err) => match self.de.recover() {
+                Ok(()) => Err(err),
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+            },
+        };
+        self.offset = self.de.read.position().offset;
+        Some(r)
+    }
+}
+
+/// A deserializer that parses S-Expressions directly from its input as Serde asks for data,
+/// without building a `Value`.
+///
+/// # Examples
+///
+/// ```
+/// # use serde::Deserialize;
+/// # use serde_sexpr::Deserializer;
+/// let mut de = Deserializer::from_str("((foo 1) (bar 2))");
+/// let value = Vec::<(String, u32)>::deserialize(&mut de).unwrap();
+/// de.end().unwrap();
+/// assert_eq!(value, vec![("foo".to_string(), 1), ("bar".to_string(), 2)]);
+/// ```
+#[derive(Debug)]
+pub struct Deserializer<R> {
+    pub(crate) read: R,
+    scratch: String,
+    pub(crate) depth: usize,
+    limits: Limits,
+    comments: Vec<Span>,
+    uncounted: usize,
+    pending_open: bool,
+    brackets: Vec<(char, usize)>,
+    quotes: Vec<Quote>,
+    settings: Settings,
+}
+
+/// A list read from a quote shorthand such as `'x`, which has no parentheses in the input.
+#[derive(Debug)]
+struct Quote {
+    /// The depth of the elements of the list.
+    depth: usize,
+
+    /// The symbol that is the first element of the list, such as `quote`.
+    symbol: &'static str,
+
+    /// The number of elements of the list that have not been read yet.
+    remaining: u8,
+}
+
+impl<'a> Deserializer<StrRead<'a>> {
+    /// Creates a deserializer reading from a string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &'a str) -> Deserializer<StrRead<'a>> {
+        Deserializer::new(StrRead::new(s))
+    }
+}
+
+impl<R: BufRead> Deserializer<IoRead<R>> {
+    /// Creates a deserializer reading from a `BufRead`.
+    pub fn from_reader(reader: R) -> Deserializer<IoRead<R>> {
+        Deserializer::new(IoRead::new(reader))
+    }
+}
+
+impl<'de, R: Read<'de>> Deserializer<R> {
+    /// Creates a deserializer reading from the given source.
+    pub fn new(read: R) -> Deserializer<R> {
+        Deserializer {
+            read,
+            scratch: String::new(),
+            depth: 0,
+            limits: Limits::default(),
+            comments: Vec::new(),
+            uncounted: 0,
+            pending_open: false,
+            brackets: Vec::new(),
+            quotes: Vec::new(),
+            settings: Settings::default(),
+        }
+    }
+
+    /// Sets the limits on the input. This should be called before deserializing anything.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde::Deserialize;
+    /// # use serde_sexpr::{Deserializer, Error, Limit, Limits};
+    /// let mut de = Deserializer::from_str("((1) ((2)))");
+    /// de.set_limits(Limits {
+    ///     max_depth: 2,
+    ///     ..Limits::default()
+    /// });
+    /// match Vec::<Vec<u32>>::deserialize(&mut de) {
+    ///     Err(Error::LimitExceeded(Limit::Depth(2), Some(span))) => {
+    ///         assert_eq!(span.start.offset, 6);
+    ///     }
+    ///     r => panic!("{:?}", r),
+    /// }
+    /// ```
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+        self.read.set_max_len(limits.max_input_len);
+    }
+
+    /// Sets whether `deserialize_any` infers the types of atoms, as it does by default. See
+    /// `ValueDeserializer::set_infer_types`.
+    pub fn set_infer_types(&mut self, infer_types: bool) {
+        self.settings.infer_types = infer_types;
+    }
+
+    /// Sets whether `deserialize_any` deserializes association lists as maps. It does not by
+    /// default. See `ValueDeserializer::set_infer_maps`.
+    ///
+    /// When it does, `deserialize_any` reads a whole list into a `Value` before deserializing it.
+    pub fn set_infer_maps(&mut self, infer_maps: bool) {
+        self.settings.infer_maps = infer_maps;
+    }
+
+    /// Sets how `Option`s are expected to be written, which is `OptionEncoding::Transparent` by
+    /// default.
+    pub fn set_option_encoding(&mut self, option_encoding: OptionEncoding) {
+        self.settings.option_encoding = option_encoding;
+    }
+
+    /// Checks that only whitespace remains in the input. This should be called after
+    /// deserializing a value, to reject trailing input.
+    pub fn end(&mut self) -> Result<()> {
+        match self.peek_token()? {
+            None => Ok(()),
+            Some(TokenKind::Close) => Err(self.syntax_error(SyntaxError::UnexpectedClose)?),
+            Some(_) => Err(self.syntax_error(SyntaxError::TrailingInput)?),
+        }
+    }
+
+    /// Returns an iterator that deserializes each of the top-level S-Expressions in the input as a
+    /// `T`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn into_iter<T: Deserialize<'de>>(self) -> StreamDeserializer<'de, R, T> {
+        StreamDeserializer {
+            de: self,
+            offset: 0,
+            failed: false,
+            output: PhantomData,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Recovers from an error that happened while deserializing a top-level value that had
+    /// already been parsed, by skipping to the end of any lists it was inside.
+    fn recover(&mut self) -> Result<()> {
+        if self.pending_open {
+            self.eat_open()?;
+        }
+        while self.depth > 0 {
+            self.end_list()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the kind of the next token, skipping whitespace and comments. Quote shorthands are
+    /// returned as `TokenKind::Open`, since they stand for lists, and the parts of the list that a
+    /// quote shorthand stands for are returned as they are reached.
+    pub(crate) fn peek_token(&mut self) -> Result<Option<TokenKind>> {
+        if self.pending_open {
+            return Ok(Some(TokenKind::Open));
+        }
+        let in_quote = match self.quotes.last() {
+            Some(quote) if quote.depth == self.depth => match quote.remaining {
+                2 => return Ok(Some(TokenKind::Symbol)),
+                0 => return Ok(Some(TokenKind::Close)),
+                _ => true,
+            },
+            _ => false,
+        };
+        loop {
+            match peek_kind(&mut self.read)? {
+                Some(TokenKind::Whitespace)
+                | Some(TokenKind::LineComment)
+                | Some(TokenKind::BlockComment) => {
+                    let _ = lex_trivia(&mut self.read)?;
+                }
+                Some(TokenKind::DatumComment) => {
+                    self.check_depth()?;
+                    let start = self.read.position();
+                    self.read.discard();
+                    self.read.discard();
+                    let end = self.read.position();
+                    let remaining = self.quotes.last().map(|quote| quote.remaining);
+                    self.comments.push(Span { start, end });
+                    self.uncounted += 1;
+                    self.skip_value()?;
+                    let _ = self.comments.pop();
+                    if let (Some(quote), Some(remaining)) = (self.quotes.last_mut(), remaining) {
+                        quote.remaining = remaining;
+                    }
+                }
+                Some(TokenKind::Quote) => return Ok(Some(TokenKind::Open)),
+                Some(TokenKind::Close) => {
+                    if in_quote {
+                        return Err(self.syntax_error(SyntaxError::UnexpectedClose)?);
+                    }
+                    let ch = self.read.peek()?;
+                    match self.brackets.last() {
+                        Some(&(close, _)) if Some(close) != ch => {
+                            return Err(self.syntax_error(SyntaxError::MismatchedBracket)?)
+                        }
+                        _ => return Ok(Some(TokenKind::Close)),
+                    }
+                }
+                Some(TokenKind::Dot) if in_quote => {
+                    return Err(self.syntax_error(SyntaxError::MisplacedDot)?)
+                }
+                kind => return Ok(kind),
+            }
+        }
+    }
+
+    /// Consumes the opening bracket or quote shorthand returned as `TokenKind::Open` by
+    /// `peek_token`. Returns an error if the list starts with a `.`.
+    pub(crate) fn eat_open(&mut self) -> Result<()> {
+        if self.pending_open {
+            self.pending_open = false;
+        } else {
+            self.count_value()?;
+            self.check_depth()?;
+            match lex_quote(&mut self.read)? {
+                Some(symbol) => self.quotes.push(Quote {
+                    depth: self.depth + 1,
+                    symbol,
+                    remaining: 2,
+                }),
+                None => {
+                    let close = self.read.peek()?.and_then(closing_bracket);
+                    self.brackets.extend(close.map(|close| (close, 0)));
+                    self.read.discard();
+                }
+            }
+        }
+        self.depth += 1;
+        if self.peek_dot()? {
+            return Err(self.syntax_error(SyntaxError::MisplacedDot)?);
+        }
+        Ok(())
+    }
+
+    /// Returns an error if a list starting at the current position would be nested too deeply.
+    /// The lists that `#;` datum comments are being skipped in count towards the depth.
+    fn check_depth(&mut self) -> Result<()> {
+        let max_depth = self.limits.max_depth;
+        if self.depth + self.comments.len() < max_depth {
+            return Ok(());
+        }
+        let limit = Limit::Depth(max_depth);
+        Err(Error::LimitExceeded(limit, Some(self.token_span()?)))
+    }
+
+    /// Counts a value starting at the current position towards the length of the list it is in,
+    /// returning an error if the list is too long. The values commented out by `#;` datum
+    /// comments, and the elements of quote shorthands, are not counted.
+    fn count_value(&mut self) -> Result<()> {
+        if self.uncounted > 0 {
+            self.uncounted -= 1;
+            return Ok(());
+        }
+        match self.quotes.last() {
+            Some(quote) if quote.depth == self.depth => return Ok(()),
+            _ => {}
+        }
+        let max_len = self.limits.max_list_len;
+        match self.brackets.last_mut() {
+            Some((_, len)) if self.depth > 0 && *len >= max_len => {
+                let limit = Limit::ListLength(max_len);
+                Err(Error::LimitExceeded(limit, Some(self.token_span()?)))
+            }
+            Some((_, len)) if self.depth > 0 => {
+                *len += 1;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Puts back a `(` consumed by `eat_open`, so that it is returned by the next `peek_token`.
+    fn uneat_open(&mut self) {
+        self.pending_open = true;
+        self.depth -= 1;
+    }
+
+    /// Returns whether the next token is a lone `.`, which separates the elements of a dotted list
+    /// from its tail.
+    pub(crate) fn peek_dot(&mut self) -> Result<bool> {
+        Ok(self.peek_token()? == Some(TokenKind::Dot))
+    }
+
+    /// Consumes the `.` found by `peek_dot`.
+    pub(crate) fn eat_dot(&mut self) {
+        self.read.discard();
+    }
+
+    /// Returns an error unless the next token is the `)` that ends a dotted list.
+    pub(crate) fn expect_close(&mut self) -> Result<()> {
+        match self.peek_token()? {
+            Some(TokenKind::Close) => Ok(()),
+            None => Err(self.eof_error()?),
+            Some(_) => Err(self.syntax_error(SyntaxError::ExpectedClose)?),
+        }
+    }
+
+    /// Consumes the `)` returned by `peek_token`.
+    pub(crate) fn eat_close(&mut self) {
+        match self.quotes.last() {
+            Some(quote) if quote.depth == self.depth => {
+                let _ = self.quotes.pop();
+            }
+            _ => {
+                let _ = self.brackets.pop();
+                self.read.discard();
+            }
+        }
+        self.depth -= 1;
+        self.finish_value();
+    }
+
+    /// Records that a value has been read, for the purpose of ending the list for a quote
+    /// shorthand after its value.
+    fn finish_value(&mut self) {
+        match self.quotes.last_mut() {
+            Some(quote) if quote.depth == self.depth => quote.remaining -= 1,
+            _ => {}
+        }
+    }
+
+    /// Returns an error of the given kind, spanning the token at the current position.
+    fn syntax_error(&mut self, kind: SyntaxError) -> Result<Error> {
+        Ok(Error::Syntax(kind, self.token_span()?))
+    }
+
+    /// Consumes the token at the current position, returning its span.
+    fn token_span(&mut self) -> Result<Span> {
+        let start = self.read.position();
+        match self.read.peek()? {
+            Some(ch) if needs_quoting(ch) => self.read.discard(),
+            Some(_) => {
+                while let Some(ch) = self.read.peek()? {
+                    if needs_quoting(ch) {
+                        break;
+                    }
+                    self.read.discard();
+                }
+            }
+            None => {}
+        }
+        let end = self.read.position();
+        Ok(Span { start, end })
+    }
+
+    /// Returns the error for the end of the input being reached where a value was expected.
+    fn eof_error(&mut self) -> Result<Error> {
+        if self.depth != self.quotes.len() {
+            self.syntax_error(SyntaxError::UnclosedList)
+        } else if let Some(&span) = self.comments.last() {
+            Ok(Error::Syntax(SyntaxError::MissingDatum, span))
+        } else {
+            self.syntax_error(SyntaxError::Empty)
+        }
+    }
+
+    /// Parses a symbol, borrowing it from the input if possible and copying it into the scratch
+    /// buffer otherwise.
+    pub(crate) fn parse_atom(&mut self) -> Result<Reference<'de, '_>> {
+        self.scratch.clear();
+        let token = self.peek_token()?;
+        match self.quotes.last() {
+            Some(quote) if quote.depth == self.depth && quote.remaining == 2 => {
+                let symbol = quote.symbol;
+                self.finish_value();
+                return Ok(Reference::Borrowed(symbol));
+            }
+            _ => {}
+        }
+        let first = match token {
+            None => return Err(self.eof_error()?),
+            Some(TokenKind::Close) => return Err(self.syntax_error(SyntaxError::UnexpectedClose)?),
+            Some(TokenKind::Open) => {
+                return Err(self.syntax_error(SyntaxError::UnexpectedChar('('))?)
+            }
+            Some(TokenKind::EscapedSymbol) => '|',
+            Some(TokenKind::String) => '"',
+            Some(_) => match self.read.peek()? {
+                Some(ch) if needs_quoting(ch) => {
+                    return Err(self.syntax_error(SyntaxError::UnexpectedChar(ch))?)
+                }
+                Some(ch) => ch,
+                None => return Err(self.eof_error()?),
+            },
+        };
+        self.count_value()?;
+        let start = self.read.position();
+        let max_len = self.limits.max_symbol_len;
+        match token {
+            Some(TokenKind::EscapedSymbol) => {
+                lex_escaped_symbol(&mut self.read, &mut self.scratch, max_len)?
+            }
+            Some(TokenKind::String) => lex_string(&mut self.read, &mut self.scratch, max_len)?,
+            Some(TokenKind::Dot) => return Err(self.syntax_error(SyntaxError::MisplacedDot)?),
+            _ => {
+                self.finish_value();
+                let s = self.read.parse_symbol(&mut self.scratch, max_len)?;
+                if s.len() > max_len {
+                    return Err(symbol_len_error(start, first, max_len));
+                }
+                return Ok(s);
+            }
+        }
+        if self.scratch.len() > max_len {
+            return Err(symbol_len_error(start, first, max_len));
+        }
+        self.finish_value();
+        Ok(Reference::Copied(&self.scratch))
+    }
+
+    /// Parses the next value into a `Value`.
+    fn parse_value(&mut self) -> Result<Value> {
+        if self.peek_token()? == Some(TokenKind::Open) {
+            self.eat_open()?;
+            let mut vs = Vec::new();
+            loop {
+                if self.peek_token()? == Some(TokenKind::Close) {
+                    self.eat_close();
+                    return Ok(Value::List(vs));
+                } else if self.peek_dot()? {
+                    self.eat_dot();
+                    let tail = self.parse_value()?;
+                    self.expect_close()?;
+                    self.eat_close();
+                    return Ok(Value::dotted_list(vs, tail));
+                }
+                vs.push(self.parse_value()?);
+            }
+        } else {
+            self.parse_atom_value()
+        }
+    }
+
+    /// Parses the next atom into a `Value`: a `Str` if it is quoted, or the kind of atom it is
+    /// written as otherwise.
+    fn parse_atom_value(&mut self) -> Result<Value> {
+        let quoted = matches!(
+            self.peek_token()?,
+            Some(TokenKind::EscapedSymbol) | Some(TokenKind::String)
+        );
+        let s = self.parse_atom()?.to_string();
+        Ok(if quoted {
+            Value::Str(s)
+        } else {
+            Value::bare_atom(s)
+        })
+    }
+
+    /// Skips over the next value.
+    pub(crate) fn skip_value(&mut self) -> Result<()> {
+        let depth = self.depth;
+        // The depths of the dotted lists whose tails are being skipped.
+        let mut tails = Vec::new();
+        loop {
+            let in_tail = tails.last() == Some(&self.depth);
+            match self.peek_token()? {
+                Some(TokenKind::Open) => {
+                    self.eat_open()?;
+                    continue;
+                }
+                Some(TokenKind::Close) if self.depth > depth && !in_tail => self.eat_close(),
+                _ if self.depth > depth && !in_tail && self.peek_dot()? => {
+                    self.eat_dot();
+                    tails.push(self.depth);
+                    continue;
+                }
+                _ => {
+                    let _ = self.parse_atom()?;
+                }
+            }
+            while tails.last() == Some(&self.depth) {
+                let _ = tails.pop();
+                self.expect_close()?;
+                self.eat_close();
+            }
+            if self.depth == depth {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Skips any remaining values in the current list, including the tail of a dotted list, then
+    /// consumes its closing `)`. Returns the number of values that were skipped.
+    fn skip_rest(&mut self) -> Result<usize> {
+        let mut len = 0;
+        while self.peek_token()? != Some(TokenKind::Close) {
+            if self.peek_dot()? {
+                self.eat_dot();
+                self.skip_value()?;
+                self.expect_close()?;
+            } else {
+                self.skip_value()?;
+            }
+            len += 1;
+        }
+        self.eat_close();
+        Ok(len)
+    }
+
+    /// Skips any remaining values in the current list, then consumes its closing `)`.
+    fn end_list(&mut self) -> Result<()> {
+        let _ = self.skip_rest()?;
+        Ok(())
+    }
+
+    /// Returns an error if the current list has no more values, or continues with the tail of a
+    /// dotted list.
+    fn expect_element(&mut self, len: usize, expected: &'static str) -> Result<()> {
+        if self.peek_token()? == Some(TokenKind::Close) {
+            Err(Error::invalid_length(len, &expected))
+        } else if self.peek_dot()? {
+            Err(Error::invalid_type(
+                Unexpected::Other("dotted list"),
+                &expected,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Deserializes a `FromStr`, such as a number, from a bare atom.
+    fn deserialize_generic<T: FromStr>(&mut self, name: &'static str) -> Result<T> {
+        self.deserialize_parsed(name, |s| s.parse().ok())
+    }
+
+    /// Deserializes a bare atom with the given parsing function.
+    fn deserialize_parsed<T>(
+        &mut self,
+        name: &'static str,
+        parse: impl FnOnce(&str) -> Option<T>,
+    ) -> Result<T> {
+        match self.peek_token()? {
+            Some(TokenKind::Open) | Some(TokenKind::EscapedSymbol) | Some(TokenKind::String) => {
+                Err(Error::Invalid(name, self.parse_value()?))
+            }
+            _ => {
+                let s = self.parse_atom()?;
+                parse(&s).ok_or_else(|| Error::Invalid(name, Value::bare_atom(s.to_string())))
+            }
+        }
+    }
+
+    /// Deserializes a `FromStr`, such as a string, from a symbol, escaped symbol or string
+    /// literal.
+    fn deserialize_text<T: FromStr>(&mut self, name: &'static str) -> Result<T> {
+        if self.peek_token()? == Some(TokenKind::Open) {
+            return Err(Error::Invalid(name, self.parse_value()?));
+        }
+        let quoted = matches!(
+            self.peek_token()?,
+            Some(TokenKind::EscapedSymbol) | Some(TokenKind::String)
+        );
+        let s = self.parse_atom()?;
+        s.parse().map_err(|_| {
+            let s = s.to_string();
+            Error::Invalid(
+                name,
+                if quoted {
+                    Value::Str(s)
+                } else {
+                    Value::bare_atom(s)
+                },
+            )
+        })
+    }
+}
+
+/// Parses a float, which may be written as `+inf.0`, `-inf.0` or `+nan.0`.
+fn parse_float<T: FromStr>(s: &str) -> Option<T> {
+    let s = match s {
+        "+inf.0" => "inf",
+        "-inf.0" => "-inf",
+        "+nan.0" => "NaN",
+        s => s,
+    };
+    s.parse().ok()
+}
+
+/// Returns the error for a symbol that is too long, given the position and first character of the
+/// token it was read from.
+fn symbol_len_error(start: Position, first: char, max_len: usize) -> Error {
+    let mut end = start;
+    end.advance(first);
+    let span = Span { start, end };
+    Error::LimitExceeded(Limit::SymbolLength(max_len), Some(span))
+}
+
+impl<'de, R: Read<'de>> serde::de::Deserializer<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let infer_types = self.settings.infer_types;
+        match self.peek_token()? {
+            // Whether a list is an association list depends on all of its elements, so it has to
+            // be parsed before it can be deserialized.
+            Some(TokenKind::Open) if self.settings.infer_maps => {
+                let value = self.parse_value()?;
+                let context = Context {
+                    settings: self.settings,
+                    depth: self.depth,
+                    max_depth: self.limits.max_depth,
+                };
+                context.deserializer(value).deserialize_any(visitor)
+            }
+            Some(TokenKind::Open) if infer_types => {
+                self.eat_open()?;
+                if self.peek_token()? == Some(TokenKind::Close) {
+                    self.eat_close();
+                    return visitor.visit_unit();
+                }
+                self.uneat_open();
+                self.deserialize_seq(visitor)
+            }
+            Some(TokenKind::Open) => self.deserialize_seq(visitor),
+            Some(TokenKind::EscapedSymbol) | Some(TokenKind::String) => {
+                self.deserialize_str(visitor)
+            }
+            _ => {
+                let s = self.parse_atom()?;
+                match Value::typed_atom(&s) {
+                    Some(Value::Int(n)) if infer_types => visit_int(n, visitor),
+                    Some(Value::Float(f)) if infer_types => visitor.visit_f64(f),
+                    Some(Value::Bool(b)) if infer_types => visitor.visit_bool(b),
+                    _ => match s {
+                        Reference::Borrowed(s) => visitor.visit_borrowed_str(s),
+                        Reference::Copied(s) => visitor.visit_str(s),
+                    },
+                }
+            }
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(self.deserialize_generic("bool")?)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(self.deserialize_generic("i8")?)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i16(self.deserialize_generic("i16")?)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32(self.deserialize_generic("i32")?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64(self.deserialize_generic("i64")?)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i128(self.deserialize_generic("i128")?)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(self.deserialize_generic("u8")?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u16(self.deserialize_generic("u16")?)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u32(self.deserialize_generic("u32")?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u64(self.deserialize_generic("u64")?)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u128(self.deserialize_generic("u128")?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f32(self.deserialize_parsed("f32", parse_float)?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f64(self.deserialize_parsed("f64", parse_float)?)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_char(self.deserialize_text("char")?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.peek_token()? == Some(TokenKind::Open) {
+            return Err(Error::Invalid("string", self.parse_value()?));
+        }
+        match self.parse_atom()? {
+            Reference::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Reference::Copied(s) => visitor.visit_str(s),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.peek_token()? == Some(TokenKind::Open) {
+            return Err(Error::Invalid("string", self.parse_value()?));
+        }
+        match self.parse_atom()? {
+            Reference::Borrowed(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+            Reference::Copied(s) => visitor.visit_bytes(s.as_bytes()),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let encoding = self.settings.option_encoding;
+        if self.peek_token()? == Some(TokenKind::Open) {
+            self.eat_open()?;
+            if self.peek_token()? == Some(TokenKind::Close) {
+                self.eat_close();
+                return match encoding {
+                    OptionEncoding::Tagged => {
+                        Err(Error::Invalid("option", Value::List(Vec::new())))
+                    }
+                    _ => visitor.visit_none(),
+                };
+            }
+            let len = match encoding {
+                OptionEncoding::Transparent => {
+                    self.uneat_open();
+                    return visitor.visit_some(self);
+                }
+                OptionEncoding::Wrapped => 1,
+                OptionEncoding::Tagged => {
+                    let tag = self.parse_value()?;
+                    if tag != Value::Sym("some".to_string()) {
+                        return Err(Error::Invalid("option", tag));
+                    }
+                    self.expect_element(1, "an option")?;
+                    2
+                }
+            };
+            let value = visitor.visit_some(&mut *self)?;
+            let rest = self.skip_rest()?;
+            if rest == 0 {
+                Ok(value)
+            } else {
+                Err(Error::invalid_length(len + rest, &"an option"))
+            }
+        } else {
+            match encoding {
+                OptionEncoding::Transparent => visitor.visit_some(self),
+                OptionEncoding::Wrapped => Err(Error::Invalid("option", self.parse_value()?)),
+                OptionEncoding::Tagged => match self.parse_value()? {
+                    Value::Sym(ref s) if s == "none" => visitor.visit_none(),
+                    value => Err(Error::Invalid("option", value)),
+                },
+            }
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.peek_token()? == Some(TokenKind::Open) {
+            self.eat_open()?;
+            if self.peek_token()? == Some(TokenKind::Close) {
+                self.eat_close();
+                return visitor.visit_unit();
+            }
+            self.uneat_open();
+        }
+        Err(Error::Invalid("unit", self.parse_value()?))
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.peek_token()? != Some(TokenKind::Open) {
+            return Err(Error::Invalid("sequence", self.parse_value()?));
+        }
+        self.eat_open()?;
+        let value = visitor.visit_seq(&mut *self)?;
+        self.end_list()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.peek_token()? != Some(TokenKind::Open) {
+            return Err(Error::Invalid("map", self.parse_value()?));
+        }
+        self.eat_open()?;
+        let value = visitor.visit_map(&mut *self)?;
+        self.end_list()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        if self.peek_token()? == Some(TokenKind::Open) {
+            self.eat_open()?;
+            if self.peek_token()? == Some(TokenKind::Close) {
+                self.eat_close();
+                return Err(Error::Invalid("enum", Value::List(Vec::new())));
+            }
+            let value = visitor.visit_enum(StreamVariantAccess(self, true))?;
+            self.end_list()?;
+            Ok(value)
+        } else {
+            visitor.visit_enum(StreamVariantAccess(self, false))
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.skip_value()?;
+        visitor.visit_unit()
+    }
+}
+
+impl<'de, R: Read<'de>> serde::de::MapAccess<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    fn next_key_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.peek_token()? {
+            Some(TokenKind::Close) => Ok(None),
+            Some(TokenKind::Open) => {
+                self.eat_open()?;
+                self.expect_element(0, "a pair")?;
+                seed.deserialize(&mut **self).map(Some)
+            }
+            _ => Err(Error::Invalid("pair", self.parse_value()?)),
+        }
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value> {
+        if self.peek_dot()? {
+            self.eat_dot();
+            let value = seed.deserialize(&mut **self)?;
+            self.expect_close()?;
+            self.eat_close();
+            return Ok(value);
+        }
+        self.expect_element(1, "a pair")?;
+        let value = seed.deserialize(&mut **self)?;
+        let len = 2 + self.skip_rest()?;
+        if len == 2 {
+            Ok(value)
+        } else {
+            Err(Error::invalid_length(len, &"a pair"))
+        }
+    }
+}
+
+impl<'de, R: Read<'de>> serde::de::SeqAccess<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.peek_token()? == Some(TokenKind::Close) {
+            Ok(None)
+        } else if self.peek_dot()? {
+            Err(Error::invalid_type(
+                Unexpected::Other("dotted list"),
+                &"a list",
+            ))
+        } else {
+            seed.deserialize(&mut **self).map(Some)
+        }
+    }
+}
+
+/// The `EnumAccess` and `VariantAccess` for `Deserializer`. The flag is whether the variant is
+/// inside a list, rather than being a bare symbol.
+struct StreamVariantAccess<'a, R>(&'a mut Deserializer<R>, bool);
+
+impl<'de, 'a, R: Read<'de>> serde::de::EnumAccess<'de> for StreamVariantAccess<'a, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<(T::Value, Self)> {
+        let variant = seed.deserialize(&mut *self.0)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, R: Read<'de>> serde::de::VariantAccess<'de> for StreamVariantAccess<'a, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        if !self.1 {
+            return Err(Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            ));
+        }
+        self.0.expect_element(0, "a newtype variant")?;
+        seed.deserialize(self.0)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        if !self.1 {
+            return Err(Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            ));
+        }
+        visitor.visit_seq(self.0)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        if !self.1 {
+            return Err(Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            ));
+        }
+        visitor.visit_map(self.0)
     }
 }
@@ -0,0 +1,1218 @@
+use crate::{
+    closing_bracket, is_closing_bracket, parser::check_input_len, Bracket, Error, Lexer, Limit,
+    Limits, ParseOptions, Span, SyntaxError, TokenKind, Value,
+};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult, Write},
+    hash::{Hash, Hasher},
+    iter::Chain,
+    mem::{replace, take},
+    option,
+    slice::{Iter, IterMut},
+    str::FromStr,
+};
+
+/// A lossless syntax tree of an S-Expression, which keeps its whitespace, comments and the
+/// original spelling of its atoms.
+///
+/// A document can be edited in place and printed back with `Display`. Anything that was not
+/// edited is printed exactly as it was written.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_sexpr::{sexpr, Document, Node};
+/// let mut doc = r#"; settings
+/// (config
+///   (name "my app")   ; the name
+///   (port 80))"#
+///     .parse::<Document>()
+///     .unwrap();
+///
+/// let config = doc.root_mut().as_list_mut().unwrap();
+/// let port = config.find_mut("port").unwrap().as_list_mut().unwrap();
+/// port.get_mut(1).unwrap().set_value(&sexpr!(8080)).unwrap();
+/// config.push(Node::atom("debug"));
+///
+/// assert_eq!(
+///     doc.to_string(),
+///     r#"; settings
+/// (config
+///   (name "my app")   ; the name
+///   (port 8080)
+///   debug)"#
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Document {
+    root: Node,
+    trailing: String,
+}
+
+impl Document {
+    /// Parses a document with the given options. The input is checked exactly as
+    /// `Value::parse_with` checks it, so it returns the same errors.
+    pub fn parse_with(s: &str, options: ParseOptions) -> Result<Document, Error> {
+        let (document, errors) = Document::parse_recovering(s, options);
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(document),
+        }
+    }
+
+    /// Parses a document, carrying on after syntax errors rather than stopping at the first one.
+    /// Returns a best-effort document along with every error that was found, in the order they
+    /// were found. The first error is the one `Document::parse_with` would return.
+    ///
+    /// Tokens that fail to lex, such as string literals with invalid escapes, become error nodes,
+    /// as do values nested too deeply and the missing value after a quote shorthand. Lists that
+    /// are never closed are finished at the end of the input or at a closing bracket that matches
+    /// a list further out. Other input with no place in the tree, such as stray closing brackets
+    /// and input after the root node, is skipped, and is kept in the prefix of the next node so
+    /// that the document still prints exactly as it was written. The only exception is input that
+    /// is longer than the limit, of which nothing is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_sexpr::{Document, NodeKind, ParseOptions};
+    /// let s = "(a \"b\\q\" (c . d e) f]";
+    /// let (doc, errors) = Document::parse_recovering(s, ParseOptions::default());
+    /// let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     errors,
+    ///     [
+    ///         "syntax error at line 1, column 6: invalid escape sequence",
+    ///         "syntax error at line 1, column 17: expected a closing bracket after the tail of a \
+    ///          dotted list",
+    ///         "syntax error at line 1, column 21: mismatched closing bracket",
+    ///         "syntax error at line 1, column 22: unclosed list",
+    ///     ]
+    /// );
+    ///
+    /// assert_eq!(doc.to_string(), s);
+    /// let list = doc.root().as_list().unwrap();
+    /// assert_eq!(list.get(1).unwrap().kind(), &NodeKind::Error("\"b\\q\"".to_string()));
+    /// assert_eq!(doc.root().to_value(), "(a (c . d) f)".parse().unwrap());
+    /// ```
+    pub fn parse_recovering(s: &str, options: ParseOptions) -> (Document, Vec<Error>) {
+        if let Err(err) = check_input_len(s, options.limits) {
+            let root = Node {
+                prefix: String::new(),
+                kind: NodeKind::Error(String::new()),
+            };
+            let document = Document {
+                root,
+                trailing: String::new(),
+            };
+            return (document, vec![err]);
+        }
+
+        let builder = Builder {
+            lexer: Lexer::new(s),
+            limits: options.limits,
+            errors: Vec::new(),
+            prefix: String::new(),
+            trailing: false,
+        };
+        builder.parse()
+    }
+
+    /// Returns the root node. Its prefix holds any whitespace and comments before it.
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Returns the root node mutably.
+    pub fn root_mut(&mut self) -> &mut Node {
+        &mut self.root
+    }
+
+    /// Returns the whitespace and comments after the root node.
+    pub fn trailing(&self) -> &str {
+        &self.trailing
+    }
+
+    /// Sets the whitespace and comments after the root node. The text is printed as it is, so it
+    /// should only contain whitespace and comments.
+    pub fn set_trailing(&mut self, trailing: impl Into<String>) {
+        self.trailing = trailing.into();
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "{}{}", self.root, self.trailing)
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Document, Error> {
+        Document::parse_with(s, ParseOptions::default())
+    }
+}
+
+/// A node of a `Document`, along with the whitespace and comments before it.
+///
+/// As with `Value`, dropping, cloning, comparing, hashing and printing a node does not recurse, so
+/// documents parsed with a large `Limits::max_depth` can be handled without overflowing the stack.
+#[derive(Debug)]
+pub struct Node {
+    prefix: String,
+    kind: NodeKind,
+}
+
+/// The kinds of node in a `Document`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum NodeKind {
+    /// A symbol or string literal.
+    Atom(Atom),
+
+    /// A list, including dotted lists.
+    List(List),
+
+    /// A quote shorthand such as `'x`.
+    Quote(Quote),
+
+    /// Input that could not be parsed, exactly as it was written. These are only found in
+    /// documents from `Document::parse_recovering`.
+    Error(String),
+}
+
+impl Node {
+    /// Creates a node with no prefix that prints as the given value. Returns the parser's error if
+    /// the value prints as text that does not parse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_sexpr::{sexpr, Node};
+    /// let node = Node::new(&sexpr!((a "b c"))).unwrap();
+    /// assert_eq!(node.to_string(), "(a |b\\ c|)");
+    /// ```
+    pub fn new(value: &Value) -> Result<Node, Error> {
+        let options = ParseOptions {
+            keep_brackets: false,
+            limits: Limits {
+                max_depth: usize::MAX,
+                ..Limits::default()
+            },
+        };
+        Ok(Document::parse_with(&value.to_string(), options)?.root)
+    }
+
+    /// Creates an atom node with no prefix, quoting the string if necessary.
+    pub fn atom(s: &str) -> Node {
+        Node {
+            prefix: String::new(),
+            kind: NodeKind::Atom(Atom::new(s)),
+        }
+    }
+
+    /// Replaces the node with one that prints as the given value, keeping its prefix. Returns the
+    /// parser's error, leaving the node as it was, if the value prints as text that does not parse.
+    pub fn set_value(&mut self, value: &Value) -> Result<(), Error> {
+        let mut node = Node::new(value)?;
+        self.kind = replace(&mut node.kind, NodeKind::Error(String::new()));
+        Ok(())
+    }
+
+    /// Returns the whitespace and comments before the node.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Sets the whitespace and comments before the node. The text is printed as it is, so it
+    /// should only contain whitespace and comments.
+    pub fn set_prefix(&mut self, prefix: impl Into<String>) {
+        self.prefix = prefix.into();
+    }
+
+    /// Returns the kind of the node.
+    pub fn kind(&self) -> &NodeKind {
+        &self.kind
+    }
+
+    /// Returns the kind of the node mutably.
+    pub fn kind_mut(&mut self) -> &mut NodeKind {
+        &mut self.kind
+    }
+
+    /// Returns the node as an atom, if it is one.
+    pub fn as_atom(&self) -> Option<&Atom> {
+        match &self.kind {
+            NodeKind::Atom(atom) => Some(atom),
+            _ => None,
+        }
+    }
+
+    /// Returns the node as a list, if it is one.
+    pub fn as_list(&self) -> Option<&List> {
+        match &self.kind {
+            NodeKind::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Returns the node as a mutable list, if it is one.
+    pub fn as_list_mut(&mut self) -> Option<&mut List> {
+        match &mut self.kind {
+            NodeKind::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Converts the node to a `Value`, as parsing it with `str::parse` would.
+    ///
+    /// Error nodes, and quotes of them, are left out of the lists they are in, and a dotted list
+    /// whose tail is an error node becomes a plain list. Anything else that is or quotes an error
+    /// node becomes an empty list.
+    pub fn to_value(&self) -> Value {
+        self.value_with(false)
+            .unwrap_or_else(|| Value::List(Vec::new()))
+    }
+
+    /// Converts the node to a `Value`, or returns `None` if it is or quotes an error node.
+    pub(crate) fn value_with(&self, keep_brackets: bool) -> Option<Value> {
+        // The nodes being converted, with their remaining children and the values of the others.
+        let mut stack = vec![(self, self.children(), Vec::new())];
+        loop {
+            let (node, children, values) = stack.last_mut().unwrap();
+            if let Some(child) = children.next() {
+                stack.push((child, child.children(), Vec::new()));
+                continue;
+            }
+            let value = node.value_from(take(values), keep_brackets);
+            let _ = stack.pop();
+            match stack.last_mut() {
+                Some((_, _, values)) => values.push(value),
+                None => return value,
+            }
+        }
+    }
+
+    /// Converts the node to a `Value`, given what its children were converted to, or returns
+    /// `None` if it is or quotes an error node.
+    fn value_from(&self, mut children: Vec<Option<Value>>, keep_brackets: bool) -> Option<Value> {
+        let value = match &self.kind {
+            NodeKind::Atom(atom) if atom.is_quoted() => Value::Str(atom.value()),
+            NodeKind::Atom(atom) => Value::bare_atom(atom.value()),
+            NodeKind::List(list) => {
+                let tail = match list.tail {
+                    Some(_) => children.pop().flatten(),
+                    None => None,
+                };
+                let vs = children.into_iter().flatten().collect();
+                match (tail, Bracket::from_open(list.open)) {
+                    (Some(tail), _) => Value::dotted_list(vs, tail),
+                    (None, Some(bracket)) if keep_brackets => Value::Bracketed(bracket, vs),
+                    (None, _) => Value::List(vs),
+                }
+            }
+            NodeKind::Quote(quote) => Value::List(vec![
+                Value::Sym(quote.symbol().to_string()),
+                children.pop().flatten()?,
+            ]),
+            NodeKind::Error(_) => return None,
+        };
+        Some(value)
+    }
+
+    /// Returns an iterator over the elements of a list followed by the tail of a dotted list, or
+    /// over the node a quote quotes.
+    fn children(&self) -> Children<'_> {
+        match &self.kind {
+            NodeKind::List(list) => list.items.iter().chain(list.tail()),
+            NodeKind::Quote(quote) => [].iter().chain(Some(&*quote.node)),
+            _ => [].iter().chain(None),
+        }
+    }
+
+    /// Returns an iterator over the node and its descendants, in pre-order.
+    fn descendants(&self) -> impl Iterator<Item = &Node> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children().rev());
+            Some(node)
+        })
+    }
+
+    /// Returns the prefix and shape of the node, which determine the node once those of its
+    /// descendants are known.
+    fn shape(&self) -> (&str, Shape<'_>) {
+        let shape = match &self.kind {
+            NodeKind::Atom(atom) => Shape::Atom(&atom.text),
+            NodeKind::List(list) => Shape::List {
+                open: list.open,
+                len: list.items.len(),
+                dot: list.tail.as_ref().map(|(dot, _)| &**dot),
+                suffix: &list.suffix,
+                closed: list.closed,
+            },
+            NodeKind::Quote(quote) => Shape::Quote(quote.text),
+            NodeKind::Error(text) => Shape::Error(text),
+        };
+        (&self.prefix, shape)
+    }
+
+    /// Returns a copy of the node whose children are the given copies of its children.
+    fn clone_with(&self, mut children: Vec<Node>) -> Node {
+        let kind = match &self.kind {
+            NodeKind::Atom(atom) => NodeKind::Atom(atom.clone()),
+            NodeKind::List(list) => {
+                let tail = list
+                    .tail
+                    .as_ref()
+                    .map(|(dot, _)| (dot.clone(), Box::new(children.pop().unwrap())));
+                NodeKind::List(List {
+                    open: list.open,
+                    items: children,
+                    tail,
+                    suffix: list.suffix.clone(),
+                    closed: list.closed,
+                })
+            }
+            NodeKind::Quote(quote) => NodeKind::Quote(Quote {
+                text: quote.text,
+                node: Box::new(children.pop().unwrap()),
+            }),
+            NodeKind::Error(text) => NodeKind::Error(text.clone()),
+        };
+        Node {
+            prefix: self.prefix.clone(),
+            kind,
+        }
+    }
+
+    /// Moves the children of the node onto `stack`.
+    fn take_children(&mut self, stack: &mut Vec<Node>) {
+        match &mut self.kind {
+            NodeKind::List(list) => {
+                stack.append(&mut list.items);
+                stack.extend(list.tail.take().map(|(_, tail)| *tail));
+            }
+            NodeKind::Quote(quote) => {
+                let empty = Node {
+                    prefix: String::new(),
+                    kind: NodeKind::Error(String::new()),
+                };
+                stack.push(replace(&mut *quote.node, empty));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The iterator returned by `Node::children`.
+type Children<'a> = Chain<Iter<'a, Node>, option::IntoIter<&'a Node>>;
+
+/// A node without its prefix or children, but with their number.
+#[derive(Eq, Hash, PartialEq)]
+enum Shape<'a> {
+    /// An atom, with its text.
+    Atom(&'a str),
+
+    /// A list, with the number of its elements and the prefix of the `.` of a dotted list.
+    List {
+        open: char,
+        len: usize,
+        dot: Option<&'a str>,
+        suffix: &'a str,
+        closed: bool,
+    },
+
+    /// A quote shorthand.
+    Quote(&'a str),
+
+    /// An error node, with its text.
+    Error(&'a str),
+}
+
+impl Clone for Node {
+    fn clone(&self) -> Node {
+        // The nodes being cloned, with their remaining children and the clones of the others.
+        let mut stack = vec![(self, self.children(), Vec::new())];
+        loop {
+            let (node, children, clones) = stack.last_mut().unwrap();
+            if let Some(child) = children.next() {
+                stack.push((child, child.children(), Vec::new()));
+                continue;
+            }
+            let cloned = node.clone_with(take(clones));
+            let _ = stack.pop();
+            match stack.last_mut() {
+                Some((_, _, clones)) => clones.push(cloned),
+                None => return cloned,
+            }
+        }
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        // Move the descendants onto a stack, so they are dropped once they have no children.
+        let mut stack = Vec::new();
+        self.take_children(&mut stack);
+        while let Some(mut node) = stack.pop() {
+            node.take_children(&mut stack);
+        }
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        self.descendants()
+            .map(Node::shape)
+            .eq(other.descendants().map(Node::shape))
+    }
+}
+
+impl Eq for Node {}
+
+impl Hash for Node {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for node in self.descendants() {
+            node.shape().hash(state);
+        }
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        // The lists being printed, with their remaining elements and tail.
+        let mut stack = Vec::new();
+        let mut next = Some(self);
+        loop {
+            if let Some(node) = next.take() {
+                fmt.write_str(&node.prefix)?;
+                match &node.kind {
+                    NodeKind::Atom(atom) => fmt.write_str(&atom.text)?,
+                    NodeKind::List(list) => {
+                        fmt.write_char(list.open)?;
+                        stack.push((list, list.items.iter(), list.tail.as_ref()));
+                    }
+                    NodeKind::Quote(quote) => {
+                        fmt.write_str(quote.text)?;
+                        next = Some(&quote.node);
+                        continue;
+                    }
+                    NodeKind::Error(text) => fmt.write_str(text)?,
+                }
+            }
+
+            let (list, items, tail) = match stack.last_mut() {
+                Some(frame) => frame,
+                None => return Ok(()),
+            };
+            if let Some(item) = items.next() {
+                next = Some(item);
+            } else if let Some((dot, tail)) = tail.take() {
+                write!(fmt, "{}.", dot)?;
+                next = Some(tail);
+            } else {
+                fmt.write_str(&list.suffix)?;
+                if list.closed {
+                    fmt.write_char(list.close())?;
+                }
+                let _ = stack.pop();
+            }
+        }
+    }
+}
+
+/// An atom in a `Document`, as it is spelled in the input.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Atom {
+    text: String,
+}
+
+impl Atom {
+    /// Creates an atom for the given string, quoting it if it cannot be written bare or would be
+    /// read as a number, boolean or keyword, as `Value::Sym` is printed.
+    pub fn new(s: &str) -> Atom {
+        Atom {
+            text: Value::Sym(s.to_string()).to_string(),
+        }
+    }
+
+    /// Returns the text of the atom, exactly as it is written.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns whether the atom is an escaped symbol or string literal, which stands for a
+    /// `Value::Str`, rather than a bare atom.
+    pub fn is_quoted(&self) -> bool {
+        self.text.starts_with(['|', '"'])
+    }
+
+    /// Returns the string the atom stands for, with any escapes decoded.
+    pub fn value(&self) -> String {
+        let mut lexer = Lexer::new(&self.text);
+        let mut s = String::new();
+        let r = match lexer.peek() {
+            Some(b'|') => lexer.escaped_sym(&mut s),
+            Some(b'"') => lexer.string(&mut s),
+            _ => return self.text.clone(),
+        };
+        r.expect("an atom failed to lex");
+        s
+    }
+}
+
+/// A list in a `Document`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct List {
+    open: char,
+    items: Vec<Node>,
+
+    /// The tail of a dotted list, and the whitespace and comments before its `.`.
+    tail: Option<(String, Box<Node>)>,
+
+    /// The whitespace and comments before the closing bracket.
+    suffix: String,
+
+    /// Whether the list has its closing bracket, which it only lacks in documents from
+    /// `Document::parse_recovering`.
+    closed: bool,
+}
+
+impl List {
+    /// Returns the opening bracket of the list: `(`, `[` or `{`.
+    pub fn open(&self) -> char {
+        self.open
+    }
+
+    /// Returns the closing bracket of the list: `)`, `]` or `}`.
+    pub fn close(&self) -> char {
+        closing_bracket(self.open).unwrap()
+    }
+
+    /// Returns whether the list has its closing bracket. Only lists in documents from
+    /// `Document::parse_recovering` can be unclosed.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Returns the number of elements in the list, not counting the tail of a dotted list.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns whether the list has no elements, not counting the tail of a dotted list.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the element at the given index.
+    pub fn get(&self, index: usize) -> Option<&Node> {
+        self.items.get(index)
+    }
+
+    /// Returns the element at the given index mutably.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Node> {
+        self.items.get_mut(index)
+    }
+
+    /// Returns an iterator over the elements of the list, not including the tail of a dotted list.
+    pub fn iter(&self) -> Iter<'_, Node> {
+        self.items.iter()
+    }
+
+    /// Returns an iterator over the elements of the list mutably.
+    pub fn iter_mut(&mut self) -> IterMut<'_, Node> {
+        self.items.iter_mut()
+    }
+
+    /// Returns the tail of the list, if it is a dotted list.
+    pub fn tail(&self) -> Option<&Node> {
+        self.tail.as_ref().map(|(_, tail)| &**tail)
+    }
+
+    /// Returns the tail of the list mutably, if it is a dotted list.
+    pub fn tail_mut(&mut self) -> Option<&mut Node> {
+        self.tail.as_mut().map(|(_, tail)| &mut **tail)
+    }
+
+    /// Returns the first element that is a list starting with an atom for `key`, as in an
+    /// association list.
+    pub fn find(&self, key: &str) -> Option<&Node> {
+        self.items.iter().find(|node| is_entry(node, key))
+    }
+
+    /// Returns the first element that is a list starting with an atom for `key` mutably.
+    pub fn find_mut(&mut self, key: &str) -> Option<&mut Node> {
+        self.items.iter_mut().find(|node| is_entry(node, key))
+    }
+
+    /// Appends an element to the list, before the tail if it is a dotted list. If the element has
+    /// no prefix, it is laid out like the current last element.
+    pub fn push(&mut self, node: Node) {
+        self.insert(self.items.len(), node);
+    }
+
+    /// Inserts an element into the list at the given index. If the element has no prefix, it is
+    /// given the line break and indentation (but not the comments) before the element it is
+    /// inserted before, or after if it is inserted at the end, or a single space if there are
+    /// none.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the list.
+    pub fn insert(&mut self, index: usize, mut node: Node) {
+        if node.prefix.is_empty() {
+            let neighbour = self.items.get(index).or_else(|| self.items.last());
+            node.prefix = neighbour.map_or_else(String::new, |n| layout(&n.prefix).to_string());
+            if node.prefix.is_empty() && index > 0 {
+                node.prefix = " ".to_string();
+            }
+        }
+        if let Some(next) = self.items.get_mut(index) {
+            if next.prefix.is_empty() {
+                next.prefix = " ".to_string();
+            }
+        }
+        self.items.insert(index, node);
+    }
+
+    /// Removes and returns the element at the given index. If it was the first element, the next
+    /// element takes its prefix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Node {
+        let mut node = self.items.remove(index);
+        if index == 0 {
+            if let Some(next) = self.items.first_mut() {
+                next.prefix = take(&mut node.prefix);
+            }
+        }
+        node
+    }
+}
+
+/// Returns the line break and indentation at the end of a prefix, or `" "` if it does not end with
+/// whitespace after a line break.
+fn layout(prefix: &str) -> &str {
+    if prefix.is_empty() {
+        return "";
+    }
+    let start = prefix.rfind('\n').unwrap_or(0);
+    match &prefix[start..] {
+        s if s.starts_with('\n') && s[1..].chars().all(char::is_whitespace) => s,
+        _ => " ",
+    }
+}
+
+/// Returns whether the node is a list whose first element is an atom for `key`.
+fn is_entry(node: &Node, key: &str) -> bool {
+    match node.as_list().and_then(|list| list.get(0)?.as_atom()) {
+        Some(atom) => atom.value() == key,
+        None => false,
+    }
+}
+
+/// A quote shorthand in a `Document`, such as `'x`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Quote {
+    text: &'static str,
+    node: Box<Node>,
+}
+
+impl Quote {
+    /// Returns the shorthand, such as `'` or `,@`.
+    pub fn shorthand(&self) -> &str {
+        self.text
+    }
+
+    /// Returns the symbol the shorthand stands for, such as `quote`.
+    pub fn symbol(&self) -> &'static str {
+        match self.text {
+            "'" => "quote",
+            "`" => "quasiquote",
+            ",@" => "unquote-splicing",
+            _ => "unquote",
+        }
+    }
+
+    /// Returns the quoted node.
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    /// Returns the quoted node mutably.
+    pub fn node_mut(&mut self) -> &mut Node {
+        &mut self.node
+    }
+}
+
+/// A node that is still being built, on the stack of `Builder`.
+enum Frame {
+    /// The whole input, which holds its root node once that has been parsed.
+    Top(Option<Node>),
+
+    /// A list, with its prefix.
+    List {
+        prefix: String,
+        list: List,
+
+        /// The prefix of the `.` of a dotted list, once it has been reached and until the tail
+        /// has been parsed.
+        dot: Option<String>,
+
+        /// Whether the list has been reported as too long.
+        too_long: bool,
+
+        /// Whether input after the tail of the list has been reported.
+        after_tail: bool,
+    },
+
+    /// A quote shorthand, with its prefix.
+    Quote(String, &'static str),
+
+    /// A datum comment, or input that is being skipped after an error, with the prefix before it
+    /// and the offset it starts at. Once its value has been parsed, its text is added to the
+    /// prefix of whatever comes next.
+    Skip(String, usize),
+}
+
+/// The parser behind `Document::parse_recovering`. It makes the same checks in the same order as
+/// the parser behind `Value::parse_with`, so the first error it finds is the one that parser
+/// returns, but it carries on after each error.
+struct Builder<'a> {
+    lexer: Lexer<'a>,
+    limits: Limits,
+    errors: Vec<Error>,
+
+    /// The whitespace, comments and skipped input since the end of the last node.
+    prefix: String,
+
+    /// Whether input after the root node has been reported.
+    trailing: bool,
+}
+
+impl<'a> Builder<'a> {
+    fn parse(mut self) -> (Document, Vec<Error>) {
+        let max_depth = self.limits.max_depth;
+        let mut stack = vec![Frame::Top(None)];
+        loop {
+            self.trivia();
+
+            let depth = stack.len() - 1;
+            if self.lexer.rest().starts_with(b"#;") {
+                if depth >= max_depth {
+                    self.error(self.lexer.limit_error(Limit::Depth(max_depth)));
+                    let start = self.lexer.offset();
+                    self.lexer.bump(2);
+                    self.skip_value();
+                    self.prefix
+                        .push_str(&self.lexer.input()[start..self.lexer.offset()]);
+                    continue;
+                }
+                stack.push(Frame::Skip(take(&mut self.prefix), self.lexer.offset()));
+                self.lexer.bump(2);
+                continue;
+            }
+
+            let next = self.lexer.peek();
+            let is_close = next.map_or(false, |b| is_closing_bracket(b as char));
+            match stack.last_mut() {
+                Some(Frame::Top(Some(_))) => {
+                    if next.is_none() {
+                        break;
+                    } else if is_close {
+                        self.error(self.lexer.token_error(SyntaxError::UnexpectedClose));
+                        self.skip_text(1);
+                    } else {
+                        if !self.trailing {
+                            self.trailing = true;
+                            self.error(self.lexer.token_error(SyntaxError::TrailingInput));
+                        }
+                        stack.push(Frame::Skip(take(&mut self.prefix), self.lexer.offset()));
+                    }
+                    continue;
+                }
+                Some(Frame::List {
+                    list,
+                    dot,
+                    too_long,
+                    after_tail,
+                    ..
+                }) => {
+                    let close = closing_bracket(list.open).map(|ch| ch as u8);
+                    if dot.is_none() && next.is_some() && next == close {
+                        self.lexer.bump(1);
+                        let node = self.finish_list(&mut stack, true);
+                        self.complete(&mut stack, node);
+                        continue;
+                    } else if is_close {
+                        if let Some(dot) = dot.take() {
+                            // A `.` with no tail after it is kept as text, and the list is closed
+                            // as if there had been no `.`.
+                            self.error(self.lexer.token_error(SyntaxError::UnexpectedClose));
+                            self.prefix = format!("{}.{}", dot, self.prefix);
+                        } else {
+                            self.mismatched_close(&mut stack);
+                        }
+                        continue;
+                    } else if list.tail.is_some() {
+                        if next.is_some() {
+                            if !*after_tail {
+                                *after_tail = true;
+                                self.error(self.lexer.token_error(SyntaxError::ExpectedClose));
+                            }
+                            if self.lexer.is_dot() {
+                                self.skip_text(1);
+                            } else {
+                                stack
+                                    .push(Frame::Skip(take(&mut self.prefix), self.lexer.offset()));
+                            }
+                            continue;
+                        }
+                    } else if dot.is_none() && !list.items.is_empty() && self.lexer.is_dot() {
+                        *dot = Some(take(&mut self.prefix));
+                        self.lexer.bump(1);
+                        continue;
+                    } else if list.items.len() >= self.limits.max_list_len
+                        && self.lexer.starts_value()
+                        && !*too_long
+                    {
+                        *too_long = true;
+                        let limit = Limit::ListLength(self.limits.max_list_len);
+                        self.error(self.lexer.limit_error(limit));
+                    }
+                }
+                _ => {}
+            }
+
+            let node = match next {
+                None => {
+                    let input = self.lexer.input();
+                    let in_list = stack
+                        .iter()
+                        .any(|frame| matches!(frame, Frame::List { .. }));
+                    // Of the input being skipped, only datum comments start with `#;`.
+                    let comment = stack.iter().rev().find_map(|frame| match frame {
+                        Frame::Skip(_, start) if input[*start..].starts_with("#;") => Some(*start),
+                        _ => None,
+                    });
+                    let err = match comment {
+                        _ if in_list => self.lexer.token_error(SyntaxError::UnclosedList),
+                        Some(start) => {
+                            let span = Span::at(input, start, start + 2);
+                            Error::Syntax(SyntaxError::MissingDatum, span)
+                        }
+                        None => self.lexer.token_error(SyntaxError::Empty),
+                    };
+                    self.error(err);
+                    self.finish(&mut stack);
+                    break;
+                }
+                Some(b) if depth >= max_depth && (b"'`,".contains(&b) || self.is_open()) => {
+                    // Values nested too deeply are kept as text, so that the document does not
+                    // get any deeper.
+                    self.error(self.lexer.limit_error(Limit::Depth(max_depth)));
+                    let start = self.lexer.offset();
+                    self.skip_value();
+                    self.error_node(start)
+                }
+                Some(_) if self.is_open() => {
+                    stack.push(Frame::List {
+                        prefix: take(&mut self.prefix),
+                        list: List {
+                            open: next.unwrap() as char,
+                            items: Vec::new(),
+                            tail: None,
+                            suffix: String::new(),
+                            closed: false,
+                        },
+                        dot: None,
+                        too_long: false,
+                        after_tail: false,
+                    });
+                    self.lexer.bump(1);
+                    continue;
+                }
+                Some(b'\'') | Some(b'`') | Some(b',') => {
+                    let text = match self.lexer.quote() {
+                        Some("quote") => "'",
+                        Some("quasiquote") => "`",
+                        Some("unquote-splicing") => ",@",
+                        _ => ",",
+                    };
+                    stack.push(Frame::Quote(take(&mut self.prefix), text));
+                    continue;
+                }
+                Some(_) => match self.atom(&mut stack) {
+                    Some(node) => node,
+                    None => continue,
+                },
+            };
+            self.complete(&mut stack, node);
+        }
+
+        let root = match stack.pop() {
+            Some(Frame::Top(Some(root))) => root,
+            _ => Node {
+                prefix: take(&mut self.prefix),
+                kind: NodeKind::Error(String::new()),
+            },
+        };
+        let document = Document {
+            root,
+            trailing: self.prefix,
+        };
+        (document, self.errors)
+    }
+
+    /// Parses a symbol or string literal. If there is none, the error is recorded and `None` is
+    /// returned once the input has been skipped past or the stack has been changed to carry on.
+    fn atom(&mut self, stack: &mut Vec<Frame>) -> Option<Node> {
+        let lexer = &mut self.lexer;
+        let start = lexer.offset();
+        let mut s = String::new();
+        let r = match lexer.peek() {
+            Some(b'"') => lexer.string(&mut s),
+            Some(b'|') => lexer.escaped_sym(&mut s),
+            Some(_) if lexer.is_dot() => {
+                self.error(self.lexer.token_error(SyntaxError::MisplacedDot));
+                self.skip_text(1);
+                return None;
+            }
+            _ => match lexer.symbol() {
+                Some(sym) => {
+                    s.push_str(sym);
+                    Ok(())
+                }
+                None if lexer
+                    .peek()
+                    .map_or(false, |b| is_closing_bracket(b as char)) =>
+                {
+                    self.error(self.lexer.token_error(SyntaxError::UnexpectedClose));
+                    return match stack.pop() {
+                        Some(Frame::Quote(prefix, text)) => {
+                            // A quote with nothing to quote quotes an empty error node.
+                            stack.push(Frame::Quote(prefix, text));
+                            Some(self.error_node(start))
+                        }
+                        Some(Frame::Skip(prefix, start)) => {
+                            self.prefix = prefix + &self.lexer.input()[start..self.lexer.offset()];
+                            None
+                        }
+                        frame => {
+                            stack.extend(frame);
+                            self.skip_text(1);
+                            None
+                        }
+                    };
+                }
+                None => {
+                    let ch = lexer.peek_char().unwrap();
+                    Err(lexer.token_error(SyntaxError::UnexpectedChar(ch)))
+                }
+            },
+        };
+        // As in `Value::parse_with`, a symbol that is too long is reported before any error after
+        // its first `max_symbol_len` bytes.
+        if s.len() > self.limits.max_symbol_len {
+            let input = self.lexer.input();
+            let end = start + input[start..].chars().next().map_or(0, char::len_utf8);
+            let span = Span::at(input, start, end);
+            let limit = Limit::SymbolLength(self.limits.max_symbol_len);
+            self.error(Error::LimitExceeded(limit, Some(span)));
+        }
+        if let Err(err) = r {
+            self.error(err);
+            let end = self.lexer.bad_token_end(start);
+            self.lexer.set_offset(end);
+            return Some(self.error_node(start));
+        }
+        Some(Node {
+            prefix: take(&mut self.prefix),
+            kind: NodeKind::Atom(Atom {
+                text: self.lexer.input()[start..self.lexer.offset()].to_string(),
+            }),
+        })
+    }
+
+    /// Handles a closing bracket that does not match the innermost list. If it matches a list
+    /// further out, the innermost list is finished without being closed, so that the bracket can
+    /// close the list it matches. Otherwise, the bracket is skipped.
+    fn mismatched_close(&mut self, stack: &mut Vec<Frame>) {
+        self.error(self.lexer.token_error(SyntaxError::MismatchedBracket));
+        let close = self.lexer.peek_char();
+        let matches_outer = stack.iter().rev().skip(1).any(|frame| match frame {
+            Frame::List { list, .. } => closing_bracket(list.open) == close,
+            _ => false,
+        });
+        if matches_outer {
+            let node = self.finish_list(stack, false);
+            self.complete(stack, node);
+        } else {
+            self.skip_text(1);
+        }
+    }
+
+    /// Pops the innermost list from the stack and returns its node.
+    fn finish_list(&mut self, stack: &mut Vec<Frame>, closed: bool) -> Node {
+        match stack.pop() {
+            Some(Frame::List {
+                prefix,
+                mut list,
+                dot,
+                ..
+            }) => {
+                list.suffix = take(&mut self.prefix);
+                if let Some(dot) = dot {
+                    list.suffix = format!("{}.{}", dot, list.suffix);
+                }
+                list.closed = closed;
+                Node {
+                    prefix,
+                    kind: NodeKind::List(list),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Finishes everything on the stack at the end of the input. Lists are left unclosed, and
+    /// quotes quote an empty error node.
+    fn finish(&mut self, stack: &mut Vec<Frame>) {
+        loop {
+            let node = match stack.last() {
+                Some(Frame::Top(_)) | None => return,
+                Some(Frame::List { .. }) => self.finish_list(stack, false),
+                Some(Frame::Quote(..)) => self.error_node(self.lexer.offset()),
+                Some(Frame::Skip(..)) => Node {
+                    prefix: String::new(),
+                    kind: NodeKind::Error(String::new()),
+                },
+            };
+            self.complete(stack, node);
+        }
+    }
+
+    /// Hands a finished node to the innermost unfinished one, finishing any quotes along the way.
+    fn complete(&mut self, stack: &mut Vec<Frame>, mut node: Node) {
+        loop {
+            match stack.last_mut() {
+                Some(Frame::Top(root)) => *root = Some(node),
+                Some(Frame::List { list, dot, .. }) => match dot.take() {
+                    Some(dot) => list.tail = Some((dot, Box::new(node))),
+                    None => list.items.push(node),
+                },
+                Some(Frame::Quote(..)) => {
+                    if let Some(Frame::Quote(prefix, text)) = stack.pop() {
+                        node = Node {
+                            prefix,
+                            kind: NodeKind::Quote(Quote {
+                                text,
+                                node: Box::new(node),
+                            }),
+                        };
+                    }
+                    continue;
+                }
+                Some(Frame::Skip(..)) => {
+                    if let Some(Frame::Skip(prefix, start)) = stack.pop() {
+                        self.prefix = prefix + &self.lexer.input()[start..self.lexer.offset()];
+                    }
+                }
+                None => unreachable!(),
+            }
+            return;
+        }
+    }
+
+    /// Adds whitespace and comments to the prefix. An unterminated block comment runs to the end
+    /// of the input.
+    fn trivia(&mut self) {
+        let start = self.lexer.offset();
+        if let Err(err) = self.lexer.skip_trivia() {
+            self.error(err);
+            self.lexer.set_offset(self.lexer.input().len());
+        }
+        self.prefix
+            .push_str(&self.lexer.input()[start..self.lexer.offset()]);
+    }
+
+    /// Skips over the next value, including any datum comments and quotes before it, without
+    /// checking it. A closing bracket that is not part of the value is left in place.
+    fn skip_value(&mut self) {
+        let mut values = 1;
+        let mut depth = 0;
+        while values > 0 {
+            let start = self.lexer.offset();
+            let kind = match self.lexer.next() {
+                None => return,
+                Some(Ok(token)) => token.kind,
+                Some(Err(_)) => {
+                    let end = self.lexer.bad_token_end(start);
+                    self.lexer.set_offset(end);
+                    TokenKind::Symbol
+                }
+            };
+            match kind {
+                TokenKind::DatumComment if depth == 0 => values += 1,
+                TokenKind::Open => depth += 1,
+                TokenKind::Close if depth == 0 => {
+                    self.lexer.set_offset(start);
+                    return;
+                }
+                TokenKind::Close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        values -= 1;
+                    }
+                }
+                TokenKind::Symbol | TokenKind::EscapedSymbol | TokenKind::String if depth == 0 => {
+                    values -= 1
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns an error node for the input from `start` to the current position.
+    fn error_node(&mut self, start: usize) -> Node {
+        Node {
+            prefix: take(&mut self.prefix),
+            kind: NodeKind::Error(self.lexer.input()[start..self.lexer.offset()].to_string()),
+        }
+    }
+
+    /// Adds the given number of bytes of input to the prefix, skipping over them.
+    fn skip_text(&mut self, len: usize) {
+        let start = self.lexer.offset();
+        self.prefix
+            .push_str(&self.lexer.input()[start..start + len]);
+        self.lexer.bump(len);
+    }
+
+    /// Returns whether the input continues with an opening bracket.
+    fn is_open(&self) -> bool {
+        matches!(self.lexer.peek(), Some(b'(') | Some(b'[') | Some(b'{'))
+    }
+
+    /// Records an error, unless it is the same as the last one, which happens when a token is
+    /// looked at again after the stack has been changed to carry on.
+    fn error(&mut self, err: Error) {
+        let repeated = match (self.errors.last(), &err) {
+            (Some(Error::Syntax(a, a_span)), Error::Syntax(b, b_span)) => {
+                a == b && a_span == b_span
+            }
+            (Some(Error::LimitExceeded(a, a_span)), Error::LimitExceeded(b, b_span)) => {
+                a == b && a_span == b_span
+            }
+            _ => false,
+        };
+        if !repeated {
+            self.errors.push(err);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::Value;
+use crate::{Limit, Span, Value};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 /// A convenient alias for `Result`.
@@ -16,11 +16,13 @@ pub enum Error {
     /// An invalid s-expression was found when trying to deserialize the given Serde type.
     Invalid(&'static str, Value),
 
-    /// A string failed to parse as an s-expression.
-    ParseFailed,
+    /// A limit on the size of the input or output was exceeded. If the limit was exceeded while
+    /// parsing, the span covers the token at which it was exceeded.
+    LimitExceeded(Limit, Option<Span>),
 
-    /// An s-expression was successfully parsed, but there was trailing input.
-    ParseTrailing,
+    /// A string failed to parse as an s-expression. The span covers the token at which parsing
+    /// failed.
+    Syntax(SyntaxError, Span),
 
     /// An error converting bytes to UTF-8.
     Utf8(std::str::Utf8Error),
@@ -29,16 +31,109 @@ pub enum Error {
 impl Display for Error {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
         match self {
-            Error::Custom(s) => fmt.write_str(&s),
+            Error::Custom(s) => fmt.write_str(s),
             Error::Io(err) => err.fmt(fmt),
             Error::Invalid(ty, val) => write!(fmt, "{} is not a {}", val, ty),
-            Error::ParseFailed => fmt.write_str("parsing s-expression failed"),
-            Error::ParseTrailing => fmt.write_str("parsing s-expression failed (trailing input)"),
+            Error::LimitExceeded(limit, Some(span)) => write!(fmt, "{} at {}", limit, span),
+            Error::LimitExceeded(limit, None) => limit.fmt(fmt),
+            Error::Syntax(kind, span) => write!(fmt, "syntax error at {}: {}", span, kind),
             Error::Utf8(err) => err.fmt(fmt),
         }
     }
 }
 
+/// The kind of a syntax error.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SyntaxError {
+    /// The input was empty, or contained only whitespace.
+    Empty,
+
+    /// The input ended after a `#;` datum comment, without the S-Expression it comments out.
+    MissingDatum,
+
+    /// A list was opened with `(`, `[` or `{`, but the input ended before it was closed.
+    UnclosedList,
+
+    /// A `)`, `]` or `}` was found without a matching opening bracket.
+    UnexpectedClose,
+
+    /// A list was closed with a different kind of bracket than it was opened with, such as in
+    /// `(a b]`.
+    MismatchedBracket,
+
+    /// An escaped symbol was opened with `|`, but the input ended before it was closed.
+    UnterminatedEscapedSymbol,
+
+    /// The input ended immediately after a `\` in an escaped symbol.
+    DanglingBackslash,
+
+    /// A block comment was opened with `#|`, but the input ended before it was closed.
+    UnterminatedBlockComment,
+
+    /// A string literal was opened with `"`, but the input ended before it was closed.
+    UnterminatedString,
+
+    /// A string literal contained an unknown or malformed escape sequence.
+    InvalidEscape,
+
+    /// A `.` was found somewhere other than between the elements and the tail of a dotted list.
+    MisplacedDot,
+
+    /// The tail of a dotted list was followed by something other than a closing bracket.
+    ExpectedClose,
+
+    /// A character was found where it is not allowed, such as unescaped whitespace inside an
+    /// escaped symbol.
+    UnexpectedChar(char),
+
+    /// An s-expression was successfully parsed, but there was trailing input.
+    TrailingInput,
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match self {
+            SyntaxError::Empty => fmt.write_str("empty input"),
+            SyntaxError::MissingDatum => fmt.write_str("datum comment without a datum"),
+            SyntaxError::UnclosedList => fmt.write_str("unclosed list"),
+            SyntaxError::UnexpectedClose => fmt.write_str("unexpected closing bracket"),
+            SyntaxError::MismatchedBracket => fmt.write_str("mismatched closing bracket"),
+            SyntaxError::UnterminatedEscapedSymbol => fmt.write_str("unterminated escaped symbol"),
+            SyntaxError::DanglingBackslash => fmt.write_str("dangling `\\` at end of input"),
+            SyntaxError::UnterminatedBlockComment => fmt.write_str("unterminated block comment"),
+            SyntaxError::UnterminatedString => fmt.write_str("unterminated string literal"),
+            SyntaxError::InvalidEscape => fmt.write_str("invalid escape sequence"),
+            SyntaxError::MisplacedDot => fmt.write_str("misplaced `.`"),
+            SyntaxError::ExpectedClose => {
+                fmt.write_str("expected a closing bracket after the tail of a dotted list")
+            }
+            SyntaxError::UnexpectedChar(ch) => write!(fmt, "unexpected {:?}", ch),
+            SyntaxError::TrailingInput => fmt.write_str("trailing input"),
+        }
+    }
+}
+
+impl Error {
+    /// Returns the span of the input that caused the error, if the error came from parsing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_sexpr::{Position, Value};
+    /// let err = "(foo\n  bar))".parse::<Value>().unwrap_err();
+    /// let span = err.span().unwrap();
+    /// assert_eq!(span.start, Position { offset: 11, line: 2, column: 7 });
+    /// assert_eq!(span.end, Position { offset: 12, line: 2, column: 8 });
+    /// ```
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::LimitExceeded(_, span) => *span,
+            Error::Syntax(_, span) => Some(*span),
+            _ => None,
+        }
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Error {
         Error::Io(err)
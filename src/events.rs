@@ -0,0 +1,202 @@
+use crate::{
+    lexer::TokenKind,
+    read::{IoRead, Read, Reference, StrRead},
+    Deserializer, Limits, Position, Result,
+};
+use std::io::BufRead;
+
+/// An event produced by `EventReader`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Event<'a> {
+    /// The start of a list. Quote shorthands such as `'x` produce the events of the list they
+    /// stand for, such as `(quote x)`.
+    StartList,
+
+    /// The `.` before the tail of a dotted list.
+    Dot,
+
+    /// The end of a list.
+    EndList,
+
+    /// A bare symbol.
+    Atom(&'a str),
+
+    /// An escaped symbol or string literal, with any escapes decoded.
+    Str(&'a str),
+}
+
+/// A pull parser, which reads S-Expressions one event at a time rather than building a `Value`.
+///
+/// Only the current atom and a small amount of state per level of nesting is kept in memory, so
+/// this can be used to pick a few values out of very large inputs. Several top-level
+/// S-Expressions may follow each other in the input. Each of them is read with the same syntax as
+/// `Value::parse`, and a syntax error in one is the error `Value::parse` would return for it.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_sexpr::{Event, EventReader};
+/// let mut reader = EventReader::from_str("(user (name alice) (posts (1 2 3)))");
+/// let mut names = Vec::new();
+/// while let Some((_, event)) = reader.next_event().unwrap() {
+///     match event {
+///         Event::Atom("name") => match reader.next_event().unwrap() {
+///             Some((_, Event::Atom(name))) => names.push(name.to_string()),
+///             r => panic!("{:?}", r),
+///         },
+///         Event::Atom("posts") => assert!(reader.skip_value().unwrap()),
+///         _ => {}
+///     }
+/// }
+/// assert_eq!(names, ["alice"]);
+/// ```
+#[derive(Debug)]
+pub struct EventReader<R> {
+    de: Deserializer<R>,
+
+    /// For each dotted list whose tail is being read, its depth and whether the tail has been
+    /// read yet.
+    tails: Vec<(usize, bool)>,
+}
+
+impl<'a> EventReader<StrRead<'a>> {
+    /// Creates an event reader reading from a string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &'a str) -> EventReader<StrRead<'a>> {
+        EventReader::new(StrRead::new(s))
+    }
+}
+
+impl<R: BufRead> EventReader<IoRead<R>> {
+    /// Creates an event reader reading from a `BufRead`.
+    pub fn from_reader(reader: R) -> EventReader<IoRead<R>> {
+        EventReader::new(IoRead::new(reader))
+    }
+}
+
+impl<'de, R: Read<'de>> EventReader<R> {
+    /// Creates an event reader reading from the given source.
+    pub fn new(read: R) -> EventReader<R> {
+        EventReader {
+            de: Deserializer::new(read),
+            tails: Vec::new(),
+        }
+    }
+
+    /// Sets the limits on the input. This should be called before reading any events.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.de.set_limits(limits);
+    }
+
+    /// Returns the next event and the position at which it starts, or `None` at the end of the
+    /// input.
+    ///
+    /// Syntax errors are returned as they are reached, so the events before an error may already
+    /// have been returned.
+    pub fn next_event<'s>(&'s mut self) -> Result<Option<(Position, Event<'s>)>>
+    where
+        'de: 's,
+    {
+        let de = &mut self.de;
+        let token = de.peek_token()?;
+        let position = de.read.position();
+        let tail = match self.tails.last() {
+            Some(&(depth, done)) if depth == de.depth => Some(done),
+            _ => None,
+        };
+        let event = match (token, tail) {
+            (_, Some(true)) => {
+                de.expect_close()?;
+                let _ = self.tails.pop();
+                de.eat_close();
+                self.finish_value();
+                Event::EndList
+            }
+            (None, _) if de.depth == 0 => return Ok(None),
+            (Some(TokenKind::Open), _) => {
+                de.eat_open()?;
+                Event::StartList
+            }
+            (Some(TokenKind::Close), None) if de.depth > 0 => {
+                de.eat_close();
+                self.finish_value();
+                Event::EndList
+            }
+            (_, None) if de.depth > 0 && de.peek_dot()? => {
+                de.eat_dot();
+                self.tails.push((de.depth, false));
+                Event::Dot
+            }
+            (token, _) => {
+                let quoted = matches!(
+                    token,
+                    Some(TokenKind::EscapedSymbol) | Some(TokenKind::String)
+                );
+                return self.atom(position, quoted);
+            }
+        };
+        Ok(Some((position, event)))
+    }
+
+    /// Skips over the next value without producing any of its events. If the next event would be
+    /// a `Dot`, the tail of the dotted list is skipped along with it.
+    ///
+    /// Returns `false`, without consuming anything, if there is no next value because the current
+    /// list or the input has ended.
+    pub fn skip_value(&mut self) -> Result<bool> {
+        let de = &mut self.de;
+        let token = de.peek_token()?;
+        let in_tail = match self.tails.last() {
+            Some(&(depth, done)) if depth == de.depth => {
+                if done {
+                    return Ok(false);
+                }
+                true
+            }
+            _ => false,
+        };
+        match token {
+            None if de.depth == 0 => return Ok(false),
+            Some(TokenKind::Close) if de.depth > 0 && !in_tail => return Ok(false),
+            _ if de.depth > 0 && !in_tail && de.peek_dot()? => {
+                de.eat_dot();
+                self.tails.push((de.depth, false));
+                self.de.skip_value()?;
+            }
+            _ => de.skip_value()?,
+        }
+        self.finish_value();
+        Ok(true)
+    }
+
+    /// Reads an atom as the next event.
+    fn atom<'s>(
+        &'s mut self,
+        position: Position,
+        quoted: bool,
+    ) -> Result<Option<(Position, Event<'s>)>>
+    where
+        'de: 's,
+    {
+        self.finish_value();
+        let s = match self.de.parse_atom()? {
+            Reference::Borrowed(s) => s,
+            Reference::Copied(s) => s,
+        };
+        let event = if quoted {
+            Event::Str(s)
+        } else {
+            Event::Atom(s)
+        };
+        Ok(Some((position, event)))
+    }
+
+    /// Records that a value has been read, so that a dotted list whose tail it was must end next.
+    fn finish_value(&mut self) {
+        if let Some((depth, done)) = self.tails.last_mut() {
+            if *depth == self.de.depth {
+                *done = true;
+            }
+        }
+    }
+}
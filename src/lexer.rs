@@ -0,0 +1,726 @@
+use crate::{needs_quoting, Error, Limit, Result, Span, SyntaxError};
+use std::{mem::take, ops::Range};
+
+/// The kind of a token.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TokenKind {
+    /// An opening bracket: `(`, `[` or `{`.
+    Open,
+
+    /// A closing bracket: `)`, `]` or `}`.
+    Close,
+
+    /// A lone `.`, which comes before the tail of a dotted list.
+    Dot,
+
+    /// A quote shorthand: `'`, `` ` ``, `,` or `,@`.
+    Quote,
+
+    /// A symbol that is not surrounded by `|` characters.
+    Symbol,
+
+    /// A symbol surrounded by `|` characters.
+    EscapedSymbol,
+
+    /// A string literal.
+    String,
+
+    /// A run of whitespace.
+    Whitespace,
+
+    /// A comment from `;` to the end of the line, not including the newline.
+    LineComment,
+
+    /// A block comment from `#|` to `|#`, including any nested block comments.
+    BlockComment,
+
+    /// The `#;` that starts a datum comment. The S-Expression it comments out follows as ordinary
+    /// tokens.
+    DatumComment,
+}
+
+/// A token, as produced by `Lexer`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Token<'a> {
+    /// The kind of the token.
+    pub kind: TokenKind,
+
+    /// The byte offsets of the start and end of the token in the input.
+    pub span: Range<usize>,
+
+    /// The text of the token, exactly as it appears in the input.
+    pub text: &'a str,
+}
+
+/// An iterator over the tokens of an S-Expression, including whitespace and comments.
+///
+/// This is the same lexer that `Value::parse_with` uses, so the tokens it produces are exactly
+/// the ones the parser sees. It does not check that brackets are balanced, or anything else about
+/// how the tokens fit together. After a token fails to lex, such as an unterminated string
+/// literal, the error is returned and the iterator ends.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_sexpr::{Lexer, TokenKind};
+/// let tokens = Lexer::new("(foo |bar\\ baz|) ; done")
+///     .map(|token| {
+///         let token = token.unwrap();
+///         (token.kind, token.text)
+///     })
+///     .collect::<Vec<_>>();
+/// assert_eq!(
+///     tokens,
+///     [
+///         (TokenKind::Open, "("),
+///         (TokenKind::Symbol, "foo"),
+///         (TokenKind::Whitespace, " "),
+///         (TokenKind::EscapedSymbol, "|bar\\ baz|"),
+///         (TokenKind::Close, ")"),
+///         (TokenKind::Whitespace, " "),
+///         (TokenKind::LineComment, "; done"),
+///     ]
+/// );
+///
+/// let mut lexer = Lexer::new("(foo \"bar");
+/// assert_eq!(lexer.nth(2).unwrap().unwrap().span, 4..5);
+/// assert!(lexer.next().unwrap().is_err());
+/// assert!(lexer.next().is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+    failed: bool,
+
+    /// The buffer string literals and escaped symbols are decoded into, to check their escapes.
+    scratch: String,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a lexer over the given input.
+    pub fn new(input: &'a str) -> Lexer<'a> {
+        Lexer {
+            input,
+            pos: 0,
+            failed: false,
+            scratch: String::new(),
+        }
+    }
+
+    /// Returns the byte offset of the next token.
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// Lexes the next token, returning its kind, or `None` at the end of the input.
+    fn token(&mut self) -> Result<Option<TokenKind>> {
+        let kind = match peek_kind(self)? {
+            Some(kind) => kind,
+            None => return Ok(None),
+        };
+        match kind {
+            TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment => {
+                let _ = lex_trivia(self)?;
+            }
+            TokenKind::Open | TokenKind::Close | TokenKind::Dot => self.pos += 1,
+            TokenKind::DatumComment => self.pos += 2,
+            TokenKind::Quote => {
+                let _ = lex_quote(self)?;
+            }
+            TokenKind::String | TokenKind::EscapedSymbol => {
+                let mut scratch = take(&mut self.scratch);
+                scratch.clear();
+                let r = if kind == TokenKind::String {
+                    lex_string(self, &mut scratch, usize::MAX)
+                } else {
+                    lex_escaped_symbol(self, &mut scratch, usize::MAX)
+                };
+                self.scratch = scratch;
+                r?;
+            }
+            TokenKind::Symbol => {
+                if self.symbol().is_none() {
+                    let ch = self.peek_char().unwrap();
+                    return Err(self.token_error(SyntaxError::UnexpectedChar(ch)));
+                }
+            }
+        }
+        Ok(Some(kind))
+    }
+
+    /// Skips whitespace, line comments and block comments. Datum comments are left to the caller,
+    /// since they contain values.
+    pub(crate) fn skip_trivia(&mut self) -> Result<()> {
+        loop {
+            self.pos = self.whitespace_end(self.pos);
+            if lex_trivia(self)?.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Lexes a quote shorthand, if the input continues with one, returning the symbol it stands
+    /// for.
+    pub(crate) fn quote(&mut self) -> Option<&'static str> {
+        // Reading characters from a string never fails.
+        lex_quote(self).ok().flatten()
+    }
+
+    /// Lexes a symbol that is not surrounded by `|` characters, if the input continues with one.
+    pub(crate) fn symbol(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        let end = self.symbol_end(start);
+        if end == start {
+            return None;
+        }
+        self.pos = end;
+        Some(&self.input[start..end])
+    }
+
+    /// Lexes an escaped symbol, starting at its opening `|`, and appends its contents to `out`.
+    pub(crate) fn escaped_sym(&mut self, out: &mut String) -> Result<()> {
+        lex_escaped_symbol(self, out, usize::MAX)
+    }
+
+    /// Lexes a string literal, starting at its opening `"`, and appends its contents to `out`.
+    pub(crate) fn string(&mut self, out: &mut String) -> Result<()> {
+        lex_string(self, out, usize::MAX)
+    }
+
+    /// Returns the offset of the end of the run of characters starting at `pos` that do not need
+    /// quoting. This is a faster way of finding the first character for which `needs_quoting`
+    /// returns true.
+    fn symbol_end(&self, mut pos: usize) -> usize {
+        let bytes = self.input.as_bytes();
+        while let Some(&b) = bytes.get(pos) {
+            if is_special(b) {
+                break;
+            } else if is_whitespace_lead(b) {
+                match self.input[pos..].chars().next() {
+                    Some(ch) if ch.is_whitespace() => break,
+                    _ => pos += 1,
+                }
+            } else {
+                pos += 1;
+            }
+        }
+        pos
+    }
+
+    /// Returns the offset of the end of the run of the given kind starting at the current
+    /// position.
+    fn run_end(&self, run: Run) -> usize {
+        match run {
+            Run::Whitespace => self.whitespace_end(self.pos),
+            Run::Line => self.byte_end(|b| b == b'\n'),
+            Run::Symbol => self.symbol_end(self.pos),
+            Run::String => self.byte_end(|b| b == b'"' || b == b'\\'),
+        }
+    }
+
+    /// Returns the offset of the first byte from the current position on that `stop` returns
+    /// true for, or the end of the input if there is none.
+    fn byte_end(&self, stop: impl Fn(u8) -> bool) -> usize {
+        match self.rest().iter().position(|&b| stop(b)) {
+            Some(len) => self.pos + len,
+            None => self.input.len(),
+        }
+    }
+
+    /// Returns the offset of the end of the run of whitespace starting at `pos`.
+    fn whitespace_end(&self, mut pos: usize) -> usize {
+        let bytes = self.input.as_bytes();
+        while let Some(&b) = bytes.get(pos) {
+            if matches!(b, b'\t'..=b'\r' | b' ') {
+                pos += 1;
+            } else if is_whitespace_lead(b) {
+                match self.input[pos..].chars().next() {
+                    Some(ch) if ch.is_whitespace() => pos += ch.len_utf8(),
+                    _ => break,
+                }
+            } else {
+                break;
+            }
+        }
+        pos
+    }
+
+    /// Returns the kind of the token at the current position, without lexing it.
+    fn next_kind(&self) -> Option<TokenKind> {
+        let mut chars = self.input[self.pos..].chars();
+        chars.next().map(|ch| token_kind(ch, chars.next()))
+    }
+
+    /// Returns whether the input continues with a lone `.`, rather than a symbol that starts with
+    /// `.`.
+    pub(crate) fn is_dot(&self) -> bool {
+        self.next_kind() == Some(TokenKind::Dot)
+    }
+
+    /// Returns whether the input continues with something that could be the start of a value, and
+    /// not with the end of the input or a character that cannot start a value.
+    pub(crate) fn starts_value(&self) -> bool {
+        match self.next_kind() {
+            Some(TokenKind::Symbol) => self.symbol_end(self.pos) > self.pos,
+            Some(TokenKind::Close) | Some(TokenKind::Whitespace) | Some(TokenKind::LineComment) => {
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    pub(crate) fn input(&self) -> &'a str {
+        self.input
+    }
+
+    pub(crate) fn rest(&self) -> &'a [u8] {
+        &self.input.as_bytes()[self.pos..]
+    }
+
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.rest().first().copied()
+    }
+
+    pub(crate) fn peek_char(&self) -> Option<char> {
+        match self.peek() {
+            Some(b) if b.is_ascii() => Some(char::from(b)),
+            Some(_) => self.input[self.pos..].chars().next(),
+            None => None,
+        }
+    }
+
+    /// Skips the given number of bytes, which must be a token that has already been checked.
+    pub(crate) fn bump(&mut self, len: usize) {
+        self.pos += len;
+    }
+
+    /// Moves the lexer to the given offset, even after an error.
+    pub(crate) fn set_offset(&mut self, pos: usize) {
+        self.pos = pos;
+        self.failed = false;
+    }
+
+    /// Returns where to carry on lexing after the token starting at `start` failed to lex. A
+    /// string literal runs to its closing `"`, an escaped symbol to its closing `|` or the next
+    /// closing bracket or newline, and anything else to the end of its first character.
+    pub(crate) fn bad_token_end(&self, start: usize) -> usize {
+        let bytes = self.input.as_bytes();
+        let (stops, ends): (&[u8], &[u8]) = match bytes.get(start) {
+            Some(b'"') => (b"\"", b""),
+            Some(b'|') => (b"|", b")]}\n"),
+            _ => {
+                let len = self.input[start..].chars().next().map_or(0, char::len_utf8);
+                return start + len;
+            }
+        };
+        let mut pos = start + 1;
+        while let Some(&b) = bytes.get(pos) {
+            if b == b'\\' {
+                pos += 2;
+            } else if stops.contains(&b) {
+                return pos + 1;
+            } else if ends.contains(&b) {
+                return pos;
+            } else {
+                pos += 1;
+            }
+        }
+        self.input.len()
+    }
+
+    /// Returns a syntax error of the given kind, covering the token at the current position.
+    pub(crate) fn token_error(&self, kind: SyntaxError) -> Error {
+        Error::Syntax(kind, self.token_span())
+    }
+
+    /// Returns an error for the given limit, covering the token at the current position.
+    pub(crate) fn limit_error(&self, limit: Limit) -> Error {
+        Error::LimitExceeded(limit, Some(self.token_span()))
+    }
+
+    /// Returns the span of the token at the current position: a single character that needs
+    /// quoting, or a run of characters that do not.
+    fn token_span(&self) -> Span {
+        let end = match self.peek_char() {
+            Some(ch) if needs_quoting(ch) => self.pos + ch.len_utf8(),
+            Some(_) => self.symbol_end(self.pos),
+            None => self.pos,
+        };
+        Span::at(self.input, self.pos, end)
+    }
+}
+
+impl<'a> Chars for Lexer<'a> {
+    type Mark = usize;
+
+    fn peek(&mut self) -> Result<Option<char>> {
+        Ok(self.peek_char())
+    }
+
+    fn peek2(&mut self) -> Result<Option<char>> {
+        Ok(self.input[self.pos..].chars().nth(1))
+    }
+
+    fn discard(&mut self) {
+        self.pos += self.peek_char().map_or(0, char::len_utf8);
+    }
+
+    fn mark(&self) -> usize {
+        self.pos
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::at(self.input, start, end)
+    }
+
+    fn skip_run(&mut self, run: Run) -> Result<()> {
+        self.pos = self.run_end(run);
+        Ok(())
+    }
+
+    fn take_run(&mut self, out: &mut String, run: Run, _: usize) -> Result<()> {
+        let end = self.run_end(run);
+        out.push_str(&self.input[self.pos..end]);
+        self.pos = end;
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>>;
+
+    fn next(&mut self) -> Option<Result<Token<'a>>> {
+        if self.failed {
+            return None;
+        }
+        let start = self.pos;
+        match self.token() {
+            Ok(Some(kind)) => Some(Ok(Token {
+                kind,
+                span: start..self.pos,
+                text: &self.input[start..self.pos],
+            })),
+            Ok(None) => None,
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// A source of characters that the lexing rules below are run over. `Lexer` is one, and so is
+/// every `Read` a `Deserializer` reads from, so that both split their input into tokens the same
+/// way.
+pub(crate) trait Chars {
+    /// A position in the input, that spans can be made from.
+    type Mark: Copy;
+
+    /// Returns the next character without consuming it, or `None` at the end of the input.
+    fn peek(&mut self) -> Result<Option<char>>;
+
+    /// Returns the character after the next one without consuming either, or `None` if the input
+    /// ends before it.
+    fn peek2(&mut self) -> Result<Option<char>>;
+
+    /// Consumes the character last returned by `peek`.
+    fn discard(&mut self);
+
+    /// Returns the position of the next character.
+    fn mark(&self) -> Self::Mark;
+
+    /// Returns the span between two positions.
+    fn span(&self, start: Self::Mark, end: Self::Mark) -> Span;
+
+    /// Consumes the run of characters of the given kind at the current position.
+    fn skip_run(&mut self, run: Run) -> Result<()> {
+        while let Some(ch) = self.peek()? {
+            if !run.contains(ch) {
+                break;
+            }
+            self.discard();
+        }
+        Ok(())
+    }
+
+    /// Consumes the run of characters of the given kind at the current position, appending them
+    /// to `out`. This may stop early once `out` is longer than `max_len` bytes.
+    fn take_run(&mut self, out: &mut String, run: Run, max_len: usize) -> Result<()> {
+        while out.len() <= max_len {
+            match self.peek()? {
+                Some(ch) if run.contains(ch) => out.push(ch),
+                _ => break,
+            }
+            self.discard();
+        }
+        Ok(())
+    }
+}
+
+/// A kind of run of characters that a token, or part of one, consists of.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Run {
+    /// Whitespace.
+    Whitespace,
+
+    /// The characters of a line comment, up to the end of the line.
+    Line,
+
+    /// The characters that do not need quoting in a symbol.
+    Symbol,
+
+    /// The characters in a string literal other than `"` and `\`.
+    String,
+}
+
+impl Run {
+    /// Returns whether the character can be part of a run of this kind.
+    fn contains(self, ch: char) -> bool {
+        match self {
+            Run::Whitespace => ch.is_whitespace(),
+            Run::Line => ch != '\n',
+            Run::Symbol => !needs_quoting(ch),
+            Run::String => ch != '"' && ch != '\\',
+        }
+    }
+}
+
+/// Returns the kind of the token that starts with `ch`, where `next` is the character after it.
+/// Only a token starting with `#` or `.` depends on the character after it. A character that
+/// cannot start a token at all is treated as the start of a symbol.
+fn token_kind(ch: char, next: Option<char>) -> TokenKind {
+    match ch {
+        '(' | '[' | '{' => TokenKind::Open,
+        ')' | ']' | '}' => TokenKind::Close,
+        '"' => TokenKind::String,
+        '|' => TokenKind::EscapedSymbol,
+        ';' => TokenKind::LineComment,
+        '#' if next == Some('|') => TokenKind::BlockComment,
+        '#' if next == Some(';') => TokenKind::DatumComment,
+        '\'' | '`' | ',' => TokenKind::Quote,
+        '.' if next.map_or(true, needs_quoting) => TokenKind::Dot,
+        ch if ch.is_whitespace() => TokenKind::Whitespace,
+        _ => TokenKind::Symbol,
+    }
+}
+
+/// Returns the kind of the next token without consuming it, or `None` at the end of the input.
+pub(crate) fn peek_kind<C: Chars>(chars: &mut C) -> Result<Option<TokenKind>> {
+    let ch = match chars.peek()? {
+        Some(ch) => ch,
+        None => return Ok(None),
+    };
+    let next = match ch {
+        '#' | '.' => chars.peek2()?,
+        _ => None,
+    };
+    Ok(Some(token_kind(ch, next)))
+}
+
+/// Lexes whitespace or a line or block comment, if the input continues with one, returning its
+/// kind.
+pub(crate) fn lex_trivia<C: Chars>(chars: &mut C) -> Result<Option<TokenKind>> {
+    let kind = peek_kind(chars)?;
+    match kind {
+        Some(TokenKind::Whitespace) => chars.skip_run(Run::Whitespace)?,
+        Some(TokenKind::LineComment) => chars.skip_run(Run::Line)?,
+        Some(TokenKind::BlockComment) => lex_block_comment(chars)?,
+        _ => return Ok(None),
+    }
+    Ok(kind)
+}
+
+/// Lexes a block comment, starting at its opening `#|`.
+fn lex_block_comment<C: Chars>(chars: &mut C) -> Result<()> {
+    let start = chars.mark();
+    chars.discard();
+    chars.discard();
+    let opened = chars.mark();
+    let mut depth = 1;
+    while depth > 0 {
+        match (chars.peek()?, chars.peek2()?) {
+            (Some('|'), Some('#')) => depth -= 1,
+            (Some('#'), Some('|')) => depth += 1,
+            (Some(_), _) => {
+                chars.discard();
+                continue;
+            }
+            (None, _) => {
+                let span = chars.span(start, opened);
+                return Err(Error::Syntax(SyntaxError::UnterminatedBlockComment, span));
+            }
+        }
+        chars.discard();
+        chars.discard();
+    }
+    Ok(())
+}
+
+/// Lexes a quote shorthand, if the input continues with one, returning the symbol it stands for.
+pub(crate) fn lex_quote<C: Chars>(chars: &mut C) -> Result<Option<&'static str>> {
+    let sym = match chars.peek()? {
+        Some('\'') => "quote",
+        Some('`') => "quasiquote",
+        Some(',') if chars.peek2()? == Some('@') => {
+            chars.discard();
+            "unquote-splicing"
+        }
+        Some(',') => "unquote",
+        _ => return Ok(None),
+    };
+    chars.discard();
+    Ok(Some(sym))
+}
+
+/// Lexes an escaped symbol, starting at its opening `|`, and appends its contents to `out`. Once
+/// `out` is longer than `max_len` bytes, this stops early and leaves the rest of the symbol.
+pub(crate) fn lex_escaped_symbol<C: Chars>(
+    chars: &mut C,
+    out: &mut String,
+    max_len: usize,
+) -> Result<()> {
+    chars.discard();
+    loop {
+        chars.take_run(out, Run::Symbol, max_len)?;
+        if out.len() > max_len {
+            return Ok(());
+        }
+        let start = chars.mark();
+        match chars.peek()? {
+            None => {
+                let span = chars.span(start, start);
+                return Err(Error::Syntax(SyntaxError::UnterminatedEscapedSymbol, span));
+            }
+            Some('|') => {
+                chars.discard();
+                return Ok(());
+            }
+            Some('\\') => {
+                chars.discard();
+                match chars.peek()? {
+                    Some(ch) if is_symbol_escape(chars, ch)? => {
+                        chars.discard();
+                        let end = chars.mark();
+                        match lex_escape(chars, ch)? {
+                            Some(ch) => out.push(ch),
+                            None => {
+                                let span = chars.span(start, end);
+                                return Err(Error::Syntax(SyntaxError::InvalidEscape, span));
+                            }
+                        }
+                    }
+                    Some(ch) => {
+                        out.push(ch);
+                        chars.discard();
+                    }
+                    None => {
+                        let span = chars.span(start, chars.mark());
+                        return Err(Error::Syntax(SyntaxError::DanglingBackslash, span));
+                    }
+                }
+            }
+            Some(ch) => {
+                chars.discard();
+                let span = chars.span(start, chars.mark());
+                return Err(Error::Syntax(SyntaxError::UnexpectedChar(ch), span));
+            }
+        }
+    }
+}
+
+/// Lexes a string literal, starting at its opening `"`, and appends its contents to `out`. Once
+/// `out` is longer than `max_len` bytes, this stops early and leaves the rest of the literal.
+pub(crate) fn lex_string<C: Chars>(chars: &mut C, out: &mut String, max_len: usize) -> Result<()> {
+    chars.discard();
+    loop {
+        chars.take_run(out, Run::String, max_len)?;
+        if out.len() > max_len {
+            return Ok(());
+        }
+        let start = chars.mark();
+        match chars.peek()? {
+            None => {
+                let span = chars.span(start, start);
+                return Err(Error::Syntax(SyntaxError::UnterminatedString, span));
+            }
+            Some('"') => {
+                chars.discard();
+                return Ok(());
+            }
+            Some(_) => {
+                chars.discard();
+                let ch = match chars.peek()? {
+                    Some(ch) => ch,
+                    None => {
+                        let span = chars.span(start, chars.mark());
+                        return Err(Error::Syntax(SyntaxError::DanglingBackslash, span));
+                    }
+                };
+                chars.discard();
+                let end = chars.mark();
+                match lex_escape(chars, ch)? {
+                    Some(ch) => out.push(ch),
+                    None => {
+                        let span = chars.span(start, end);
+                        return Err(Error::Syntax(SyntaxError::InvalidEscape, span));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Decodes the rest of an escape sequence in a string literal or escaped symbol, given the
+/// character after the backslash, which has already been consumed. Returns `None` if the sequence
+/// is invalid.
+fn lex_escape<C: Chars>(chars: &mut C, ch: char) -> Result<Option<char>> {
+    let ch = match ch {
+        '"' | '\\' => ch,
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '0' => '\0',
+        'u' if chars.peek()? == Some('{') => {
+            chars.discard();
+            let mut code = 0;
+            let mut digits = 0;
+            loop {
+                match chars.peek()?.and_then(|ch| ch.to_digit(16)) {
+                    Some(digit) if digits < 6 => code = code * 16 + digit,
+                    _ if digits > 0 && chars.peek()? == Some('}') => break,
+                    _ => return Ok(None),
+                }
+                digits += 1;
+                chars.discard();
+            }
+            chars.discard();
+            return Ok(char::from_u32(code));
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(ch))
+}
+
+/// Returns whether the character after a backslash in an escaped symbol, which is the next one in
+/// `chars`, starts an escape sequence rather than standing for itself. A `u` only does if it is
+/// followed by `{`, so that `|\u|` is still the symbol `u`.
+fn is_symbol_escape<C: Chars>(chars: &mut C, ch: char) -> Result<bool> {
+    match ch {
+        'n' | 'r' | 't' | '0' => Ok(true),
+        'u' => Ok(chars.peek2()? == Some('{')),
+        _ => Ok(false),
+    }
+}
+
+/// Returns whether the byte is an ASCII character that needs quoting in a symbol.
+fn is_special(b: u8) -> bool {
+    matches!(b, b'\t'..=b'\r' | b' ') || b"\"()[]{};|\\".contains(&b)
+}
+
+/// Returns whether the byte can start a non-ASCII whitespace character in UTF-8. Any other byte
+/// that is not ASCII can be skipped over without decoding the character it is part of.
+fn is_whitespace_lead(b: u8) -> bool {
+    matches!(b, 0xC2 | 0xE1 | 0xE2 | 0xE3)
+}
@@ -2,22 +2,83 @@
 //!
 //! # Examples
 //!
-//! Any characters other than `(`, `)`, `|`, `\`, and whitespace can be used in a symbol. If you
-//! want to use one of these characters, you can use an *escaped symbol,* which is surrounded by
-//! `|` characters. Within an escaped symbol, these characters can be used, escaped by a backslash.
+//! Any characters other than brackets, `;`, `|`, `"`, `\`, and whitespace can be used in a
+//! symbol. If you want to use one of these characters, you can use an *escaped symbol,* which is
+//! surrounded by `|` characters. Within an escaped symbol, these characters can be used, escaped
+//! by a backslash, as can the `\n`, `\r`, `\t`, `\0` and `\u{...}` escapes of string literals.
+//! Any other character escaped by a backslash stands for itself, including a `u` that is not
+//! followed by `{`.
+//!
+//! A *string literal* surrounded by `"` characters can be used instead of an escaped symbol, and
+//! parses to the same value. Within a string literal, `\"`, `\\`, `\n`, `\r`, `\t`, `\0` and
+//! `\u{...}` escapes can be used.
+//!
+//! ```
+//! let value: Vec<String> = serde_sexpr::from_str(r#"(|Hello,\ world!| "Hello,\tworld!")"#).unwrap();
+//! assert_eq!(value, vec!["Hello, world!", "Hello,\tworld!"]);
+//! ```
+//!
+//! Lists can be written with `[...]` or `{...}` as well as `(...)`, as long as each list is
+//! closed with the same kind of bracket that it was opened with.
+//!
+//! A `.` before the last element of a list makes it a *dotted list,* such as `(a . b)`. Map
+//! entries can be written as dotted pairs as well as two-element lists.
+//!
+//! ```
+//! # use std::collections::BTreeMap;
+//! let value: BTreeMap<String, u32> = serde_sexpr::from_str("((a . 1) (b 2))").unwrap();
+//! assert_eq!(value["a"], 1);
+//! assert_eq!(value["b"], 2);
+//! ```
+//!
+//! The shorthands `'x`, `` `x ``, `,x` and `,@x` are read as the lists `(quote x)`,
+//! `(quasiquote x)`, `(unquote x)` and `(unquote-splicing x)`.
+//!
+//! Comments are treated like whitespace. A `;` starts a comment that runs to the end of the line,
+//! `#|` and `|#` surround a block comment (which may be nested), and `#;` comments out the
+//! S-Expression that follows it.
+//!
+//! ```
+//! let s = "(1 ; one
+//!           #| two, three |# #;(four) 5)";
+//! let value: Vec<u32> = serde_sexpr::from_str(s).unwrap();
+//! assert_eq!(value, vec![1, 5]);
+//! ```
+//!
+//! Lists (including those written with quote shorthands) can be nested at most
+//! `DEFAULT_MAX_DEPTH` deep, so that deeply nested input cannot overflow the stack. Exceeding the
+//! limit when parsing, deserializing or serializing returns `Error::LimitExceeded`. The limit can
+//! be changed for serializing with `Serializer::with_max_depth`, and for deserializing a `Value`
+//! with `ValueDeserializer::set_max_depth`. For reading input from untrusted sources, `Limits` can
+//! also limit the length of the input, symbols and lists; it is accepted by `from_str_with` and
+//! the other `*_with` deserialization functions, `ParseOptions` and `Deserializer::set_limits`.
+//!
+//! ```
+//! let s = "(".repeat(100_000);
+//! assert!(serde_sexpr::from_str::<Vec<u32>>(&s).is_err());
+//! ```
+//!
+//! Input can also be read without Serde: as a `Value` with `str::parse` or `Value::parse_with`,
+//! one event at a time with `EventReader`, as tokens with `Lexer`, or as a `Document` that keeps
+//! comments and layout so that it can be edited and written back out.
+//! `Value::parse_recovering` and `Document::parse_recovering` carry on after syntax errors, to
+//! report all of them at once.
 //!
 //! ```
 //! // Serialize!
 //! let value = vec!["Hello!".to_string(), "Goodbye, world!".to_string(), ")|(".to_string()];
 //! let sexpr = serde_sexpr::to_string(&value).unwrap();
-//! assert_eq!(sexpr, "(Hello! |Goodbye,\\ world!| |\\)\\|\\(|)");
+//! assert_eq!(sexpr, "(|Hello!| |Goodbye,\\ world!| |\\)\\|\\(|)");
 //!
 //! // Deserialize!
 //! let value2: Vec<String> = serde_sexpr::from_str(&sexpr).unwrap();
 //! assert_eq!(value, value2);
 //! ```
 //!
-//! Types are serialized as follows:
+//! Types are serialized as follows. Strings and characters are always quoted, so that they can be
+//! told apart from symbols, numbers and booleans. By default, `Some(x)` is serialized as `x` and
+//! `None` as `()`, which is ambiguous for types such as `Option<Option<T>>`; see
+//! `OptionEncoding` for the alternatives.
 //!
 //! ```
 //! # use serde::Serialize;
@@ -33,12 +94,12 @@
 //! assert_eq!(serde_sexpr::to_string(&42u64).unwrap(), "42");
 //! assert_eq!(serde_sexpr::to_string(&123.45f32).unwrap(), "123.45");
 //! assert_eq!(serde_sexpr::to_string(&123.45f64).unwrap(), "123.45");
-//! assert_eq!(serde_sexpr::to_string(&'%').unwrap(), "%");
+//! assert_eq!(serde_sexpr::to_string(&'%').unwrap(), "|%|");
 //!
 //! let s1: String = "s1".to_string();
 //! let s2: &str = "s2";
-//! assert_eq!(serde_sexpr::to_string(&s1).unwrap(), "s1");
-//! assert_eq!(serde_sexpr::to_string(&s2).unwrap(), "s2");
+//! assert_eq!(serde_sexpr::to_string(&s1).unwrap(), "|s1|");
+//! assert_eq!(serde_sexpr::to_string(&s2).unwrap(), "|s2|");
 //!
 //! let b1: Vec<u8> = b"s1".to_vec();
 //! let b2: &[u8] = b"s2";
@@ -47,7 +108,7 @@
 //!
 //! let o1: Option<&str> = Some("foo");
 //! let o2: Option<&str> = None;
-//! assert_eq!(serde_sexpr::to_string(&o1).unwrap(), "foo");
+//! assert_eq!(serde_sexpr::to_string(&o1).unwrap(), "|foo|");
 //! assert_eq!(serde_sexpr::to_string(&o2).unwrap(), "()");
 //!
 //! let mut map = BTreeMap::new();
@@ -60,9 +121,9 @@
 //! let v = vec!["uno", "dos", "tres"];
 //!
 //! assert_eq!(serde_sexpr::to_string(&()).unwrap(), "()");
-//! assert_eq!(serde_sexpr::to_string(&map).unwrap(), "((1 yi) (2 er) (3 san))");
-//! assert_eq!(serde_sexpr::to_string(&t).unwrap(), "(4 five (83 73 88))");
-//! assert_eq!(serde_sexpr::to_string(&v).unwrap(), "(uno dos tres)");
+//! assert_eq!(serde_sexpr::to_string(&map).unwrap(), "((1 |yi|) (2 |er|) (3 |san|))");
+//! assert_eq!(serde_sexpr::to_string(&t).unwrap(), "(4 |five| (83 73 88))");
+//! assert_eq!(serde_sexpr::to_string(&v).unwrap(), "(|uno| |dos| |tres|)");
 //!
 //! #[derive(Serialize)]
 //! struct UnitStruct;
@@ -198,6 +259,64 @@
 //!     Foo::StructVariant { foo: 42, bar: true }
 //! );
 //! ```
+//!
+//! `deserialize_any` infers the types of atoms, so untagged enums can be used. This can be turned
+//! off with `Deserializer::set_infer_types`. Internally tagged and adjacently tagged enums and
+//! `#[serde(flatten)]` also need association lists such as `((a 1) (b 2))` to be deserialized as
+//! maps, which can be turned on with `Deserializer::set_infer_maps`.
+//!
+//! ```
+//! # use serde::{Deserialize, Serialize};
+//! # use serde_sexpr::{Deserializer, ValueDeserializer};
+//! #[derive(Debug, Deserialize, PartialEq, Serialize)]
+//! #[serde(tag = "type")]
+//! enum Shape {
+//!     Circle { radius: f64 },
+//!     Square { side: u32 },
+//! }
+//!
+//! #[derive(Debug, Deserialize, PartialEq, Serialize)]
+//! #[serde(tag = "t", content = "c")]
+//! enum Fill {
+//!     Solid(String),
+//!     Empty,
+//! }
+//!
+//! #[derive(Debug, Deserialize, PartialEq, Serialize)]
+//! struct Drawing {
+//!     name: String,
+//!     #[serde(flatten)]
+//!     shape: Shape,
+//!     fill: Fill,
+//! }
+//!
+//! let drawing = Drawing {
+//!     name: "sun".to_string(),
+//!     shape: Shape::Circle { radius: 1.5 },
+//!     fill: Fill::Solid("yellow".to_string()),
+//! };
+//! let s = serde_sexpr::to_string(&drawing).unwrap();
+//! assert_eq!(
+//!     s,
+//!     "((|name| |sun|) (|type| |Circle|) (|radius| 1.5) (|fill| ((t |Solid|) (c |yellow|))))"
+//! );
+//! let mut de = Deserializer::from_str(&s);
+//! de.set_infer_maps(true);
+//! assert_eq!(Drawing::deserialize(&mut de).unwrap(), drawing);
+//! let mut de = ValueDeserializer::new(serde_sexpr::to_value(&drawing).unwrap());
+//! de.set_infer_maps(true);
+//! assert_eq!(Drawing::deserialize(de).unwrap(), drawing);
+//!
+//! let mut de = Deserializer::from_str("((name box) (type Square) (side 2) (fill ((t Empty))))");
+//! de.set_infer_maps(true);
+//! let drawing = Drawing::deserialize(&mut de).unwrap();
+//! assert_eq!(drawing.shape, Shape::Square { side: 2 });
+//! assert_eq!(drawing.fill, Fill::Empty);
+//! ```
+// Newer compilers have made some of the lints below into hard errors and removed them, and
+// replaced `private_in_public` with `private_bounds` and `private_interfaces`, which older
+// compilers do not know.
+#![allow(renamed_and_removed_lints, unknown_lints)]
 #![deny(
     bad_style,
     bare_trait_objects,
@@ -213,7 +332,9 @@
     path_statements,
     patterns_in_fns_without_body,
     plugin_as_library,
+    private_bounds,
     private_in_public,
+    private_interfaces,
     safe_extern_statics,
     trivial_casts,
     trivial_numeric_casts,
@@ -231,23 +352,63 @@
 )]
 
 mod de;
+mod document;
 mod error;
+mod events;
+mod lexer;
+mod limits;
 #[macro_use]
 mod macros;
 mod parser;
+pub mod read;
 mod ser;
+mod span;
 #[cfg(test)]
 mod tests;
 mod value;
 
 pub use crate::{
-    de::{from_reader, from_slice, from_str, from_value},
-    error::{Error, Result},
-    ser::{to_string, to_value, to_vec, to_writer, Serializer},
-    value::Value,
+    de::{
+        from_reader, from_reader_with, from_slice, from_slice_with, from_str, from_str_with,
+        from_value, from_value_with, Deserializer, StreamDeserializer, ValueDeserializer,
+    },
+    document::{Atom, Document, List, Node, NodeKind, Quote},
+    error::{Error, Result, SyntaxError},
+    events::{Event, EventReader},
+    lexer::{Lexer, Token, TokenKind},
+    limits::{Limit, Limits},
+    parser::ParseOptions,
+    ser::{
+        to_string, to_string_with, to_value, to_vec, to_vec_with, to_writer, to_writer_with,
+        OptionEncoding, Serializer,
+    },
+    span::{Position, Span},
+    value::{Bracket, DisplayValue, PrintOptions, StringStyle, Value},
 };
 
+#[doc(hidden)]
+pub use crate::macros::literal as __sexpr_literal;
+
+/// The default maximum number of lists that can be nested inside each other when parsing,
+/// deserializing or serializing.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
 /// Returns whether the given character needs quoting.
 fn needs_quoting(ch: char) -> bool {
-    ch.is_whitespace() || "()|\\".contains(ch)
+    ch.is_whitespace() || "\"()[]{};|\\".contains(ch)
+}
+
+/// Returns the closing bracket that matches the given opening bracket, if it is one.
+fn closing_bracket(open: char) -> Option<char> {
+    match open {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        _ => None,
+    }
+}
+
+/// Returns whether the given character is a closing bracket.
+fn is_closing_bracket(ch: char) -> bool {
+    ")]}".contains(ch)
 }
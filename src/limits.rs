@@ -0,0 +1,78 @@
+use crate::DEFAULT_MAX_DEPTH;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Limits on the size of the input that is parsed or deserialized, for reading S-Expressions from
+/// untrusted sources.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_sexpr::{Error, Limit, Limits};
+/// let limits = Limits {
+///     max_list_len: 3,
+///     ..Limits::default()
+/// };
+/// assert!(serde_sexpr::from_str_with::<Vec<u32>>("(1 2 3)", limits).is_ok());
+/// match serde_sexpr::from_str_with::<Vec<u32>>("(1 2 3 4)", limits) {
+///     Err(Error::LimitExceeded(Limit::ListLength(3), Some(span))) => {
+///         assert_eq!(span.start.offset, 7);
+///     }
+///     r => panic!("{:?}", r),
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Limits {
+    /// The maximum number of lists that can be nested inside each other. Quote shorthands and
+    /// `#;` datum comments count as a level of nesting. Defaults to `DEFAULT_MAX_DEPTH`.
+    pub max_depth: usize,
+
+    /// The maximum length of the input, in bytes. No more than this many bytes are read from a
+    /// reader. Defaults to no limit.
+    pub max_input_len: usize,
+
+    /// The maximum length of a symbol, in bytes, after any escape sequences are decoded. Defaults
+    /// to no limit.
+    pub max_symbol_len: usize,
+
+    /// The maximum number of elements in a list, counting the tail of a dotted list. Defaults to
+    /// no limit.
+    pub max_list_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_input_len: usize::MAX,
+            max_symbol_len: usize::MAX,
+            max_list_len: usize::MAX,
+        }
+    }
+}
+
+/// A limit that was exceeded, with its value.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Limit {
+    /// The maximum number of lists that can be nested inside each other.
+    Depth(usize),
+
+    /// The maximum length of the input, in bytes.
+    InputLength(usize),
+
+    /// The maximum length of a symbol, in bytes.
+    SymbolLength(usize),
+
+    /// The maximum number of elements in a list.
+    ListLength(usize),
+}
+
+impl Display for Limit {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match self {
+            Limit::Depth(n) => write!(fmt, "nesting depth limit of {} exceeded", n),
+            Limit::InputLength(n) => write!(fmt, "input length limit of {} bytes exceeded", n),
+            Limit::SymbolLength(n) => write!(fmt, "symbol length limit of {} bytes exceeded", n),
+            Limit::ListLength(n) => write!(fmt, "list length limit of {} elements exceeded", n),
+        }
+    }
+}
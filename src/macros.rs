@@ -1,5 +1,10 @@
+use crate::Value;
+
 /// A macro for easily constructing `Value`s.
 ///
+/// Identifiers become symbols, except for `true` and `false`, which become booleans. Number
+/// literals become integers or floats, and string and character literals become strings.
+///
 /// # Examples
 ///
 /// ```
@@ -9,37 +14,74 @@
 /// assert_eq!(a, b);
 ///
 /// let a = sexpr!("bar");
-/// let b = Value::Sym("bar".to_string());
+/// let b = Value::Str("bar".to_string());
 /// assert_eq!(a, b);
 ///
 /// let a = sexpr!(12345);
-/// let b = Value::Sym("12345".to_string());
+/// let b = Value::Int(12345);
+/// assert_eq!(a, b);
+///
+/// let a = sexpr!((1.0 true));
+/// let b = Value::List(vec![Value::Float(1.0), Value::Bool(true)]);
 /// assert_eq!(a, b);
 ///
 /// let a = sexpr!(((a "bee") (c 0xd)));
 /// let b = Value::List(vec![
 ///     Value::List(vec![
 ///         Value::Sym("a".to_string()),
-///         Value::Sym("bee".to_string()),
+///         Value::Str("bee".to_string()),
 ///     ]),
 ///     Value::List(vec![
 ///         Value::Sym("c".to_string()),
-///         Value::Sym("13".to_string()),
+///         Value::Int(13),
 ///     ]),
 /// ]);
 /// assert_eq!(a, b);
 /// ```
 #[macro_export]
 macro_rules! sexpr {
+    ( true ) => {
+        $crate::Value::Bool(true)
+    };
+    ( false ) => {
+        $crate::Value::Bool(false)
+    };
     ( $s:ident ) => {
         $crate::Value::Sym(stringify!($s).to_string())
     };
     ( $s:literal ) => {
-        $crate::Value::Sym($s.to_string())
+        $crate::__sexpr_literal(stringify!($s), $s.to_string())
     };
     ( ($($t:tt)*) ) => {
         $crate::Value::List(vec![
             $($crate::sexpr!($t)),*
         ])
+    };
+    ( [$($t:tt)*] ) => {
+        $crate::Value::Bracketed($crate::Bracket::Square, vec![
+            $($crate::sexpr!($t)),*
+        ])
+    };
+    ( {$($t:tt)*} ) => {
+        $crate::Value::Bracketed($crate::Bracket::Curly, vec![
+            $($crate::sexpr!($t)),*
+        ])
+    };
+}
+
+/// Returns the value for a literal in `sexpr!`, given the literal as it was written and its value
+/// as a string.
+#[doc(hidden)]
+pub fn literal(token: &str, s: String) -> Value {
+    if token.starts_with(['"', '\'']) || token.starts_with("r\"") || token.starts_with("r#") {
+        return Value::Str(s);
+    }
+    // The value of a float literal such as `1.0` may be printed without a decimal point.
+    let hex = token.starts_with("0x");
+    let float = !hex
+        && (token.contains(['.', 'e', 'E']) || token.ends_with("f32") || token.ends_with("f64"));
+    match s.parse() {
+        Ok(f) if float => Value::Float(f),
+        _ => Value::bare_atom(s),
     }
 }
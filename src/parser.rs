@@ -1,50 +1,328 @@
-use crate::{needs_quoting, Error, Value};
-use nom::{
-    alt_complete, anychar, char, delimited, do_parse, eof, many0, map, named, separated_list,
-    take_while, take_while1, types::CompleteStr,
+use crate::{
+    closing_bracket, is_closing_bracket, Bracket, Document, Error, Lexer, Limit, Limits, Position,
+    Span, SyntaxError, Value,
 };
-use std::{borrow::Cow, str::FromStr};
+use std::str::FromStr;
+
+/// Options controlling how a `Value` is parsed.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ParseOptions {
+    /// Whether lists written with square or curly brackets are parsed as `Value::Bracketed`,
+    /// rather than as a `Value::List`. Dotted lists are always parsed as a `Value::DottedList`.
+    pub keep_brackets: bool,
+
+    /// Limits on the size of the input.
+    pub limits: Limits,
+}
 
 impl FromStr for Value {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Value, Error> {
-        match parser(CompleteStr(s)) {
-            Ok((rest, value)) => {
-                if rest.is_empty() {
-                    Ok(value)
-                } else {
-                    Err(Error::ParseTrailing)
-                }
-            }
-            Err(_) => Err(Error::ParseFailed),
+        Value::parse_with(s, ParseOptions::default())
+    }
+}
+
+impl Value {
+    /// Parses a value with the given options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_sexpr::{sexpr, ParseOptions, Value};
+    /// let options = ParseOptions {
+    ///     keep_brackets: true,
+    ///     ..ParseOptions::default()
+    /// };
+    /// let value = Value::parse_with("(let [x 1] x)", options).unwrap();
+    /// assert_eq!(value, sexpr!((let [x 1] x)));
+    /// assert_eq!(value.to_string(), "(let [x 1] x)");
+    ///
+    /// let value = Value::parse_with("(let [x 1] x)", ParseOptions::default()).unwrap();
+    /// assert_eq!(value.to_string(), "(let (x 1) x)");
+    /// ```
+    pub fn parse_with(s: &str, options: ParseOptions) -> Result<Value, Error> {
+        check_input_len(s, options.limits)?;
+        Parser {
+            lexer: Lexer::new(s),
+            options,
         }
+        .parse()
     }
+
+    /// Parses a value, carrying on after syntax errors rather than stopping at the first one.
+    /// Returns a best-effort value, or `None` if nothing could be made of the input, along with
+    /// every error that was found. The first error is the one `Value::parse_with` would return.
+    ///
+    /// The input is parsed as `Document::parse_recovering` parses it, and the value is made from
+    /// the document as `Node::to_value` makes it, so anything that failed to parse is left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_sexpr::{ParseOptions, Value};
+    /// let s = "(a |b c| (d . e f) g";
+    /// let (value, errors) = Value::parse_recovering(s, ParseOptions::default());
+    /// assert_eq!(value, Some("(a (d . e) g)".parse().unwrap()));
+    /// let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     errors,
+    ///     [
+    ///         "syntax error at line 1, column 6: unexpected ' '",
+    ///         "syntax error at line 1, column 17: expected a closing bracket after the tail of a \
+    ///          dotted list",
+    ///         "syntax error at line 1, column 21: unclosed list",
+    ///     ]
+    /// );
+    /// ```
+    pub fn parse_recovering(s: &str, options: ParseOptions) -> (Option<Value>, Vec<Error>) {
+        let (document, errors) = Document::parse_recovering(s, options);
+        (document.root().value_with(options.keep_brackets), errors)
+    }
+}
+
+/// Checks that the input is no longer than the limit.
+pub(crate) fn check_input_len(s: &str, limits: Limits) -> Result<(), Error> {
+    let max_len = limits.max_input_len;
+    if s.len() <= max_len {
+        return Ok(());
+    }
+    let mut end = max_len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    let pos = Position::at(s, end);
+    let span = Span {
+        start: pos,
+        end: pos,
+    };
+    Err(Error::LimitExceeded(
+        Limit::InputLength(max_len),
+        Some(span),
+    ))
+}
+
+/// A value that is still being parsed, on the parser's stack.
+enum Frame {
+    /// The whole input, which holds its value once that has been parsed.
+    Top(Option<Value>),
+
+    /// A list opened with the given bracket.
+    List {
+        open: u8,
+        vs: Vec<Value>,
+        tail: Tail,
+    },
+
+    /// A quote shorthand, which becomes a list of the given symbol and the quoted value.
+    Quote(&'static str),
+
+    /// A datum comment starting at the given offset, whose value is discarded.
+    Comment(usize),
+}
+
+/// How far a list has got towards the tail of a dotted list.
+enum Tail {
+    /// No `.` has been found, so elements are being parsed.
+    None,
+
+    /// A `.` has been found, so the tail is being parsed.
+    Expected,
+
+    /// The tail has been parsed, so only the closing bracket may follow.
+    Parsed(Value),
 }
 
-named!(parser<CompleteStr, Value>, do_parse!(ws >> v: value >> ws >> eof!() >> (v)));
-named!(value<CompleteStr, Value>, alt_complete!( list | escaped_sym | unescaped_sym ));
+/// The parser behind `Value::parse_with`. It works on the tokens of a `Lexer`, and keeps its own
+/// stack of unfinished values rather than recursing.
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    options: ParseOptions,
+}
 
-named!(list<CompleteStr, Value>, map!(delimited!(char!('('), list_body, char!(')')), Value::List));
-named!(list_body<CompleteStr, Vec<Value>>, separated_list!(ws, value));
+impl<'a> Parser<'a> {
+    /// Parses the whole input as a single value, surrounded by whitespace and comments.
+    fn parse(&mut self) -> Result<Value, Error> {
+        let limits = self.options.limits;
+        let lexer = &mut self.lexer;
+        let mut stack = vec![Frame::Top(None)];
+        loop {
+            lexer.skip_trivia()?;
 
-named!(escaped_sym<CompleteStr, Value>,
-    map!(delimited!(char!('|'), many0!(sym_chs), char!('|')),
-         |s| Value::Sym(s.into_iter().collect())));
-named!(unescaped_sym<CompleteStr, Value>,
-    map!(take_while1!(doesnt_need_quoting),
-         |s| Value::Sym(s.to_string())));
+            // The number of lists, quotes and datum comments that the next value is inside.
+            let depth = stack.len() - 1;
+            if lexer.rest().starts_with(b"#;") {
+                if depth >= limits.max_depth {
+                    return Err(lexer.limit_error(Limit::Depth(limits.max_depth)));
+                }
+                stack.push(Frame::Comment(lexer.offset()));
+                lexer.bump(2);
+                continue;
+            }
 
-named!(sym_chs<CompleteStr, Cow<str>>, alt_complete!(escaped_ch | unescaped_chs));
-named!(escaped_ch<CompleteStr, Cow<str>>, do_parse!(
-    char!('\\') >>
-    ch: anychar >>
-    (Cow::Owned(Some(ch).into_iter().collect()))));
-named!(unescaped_chs<CompleteStr, Cow<str>>,
-    map!(take_while1!(doesnt_need_quoting), |CompleteStr(s)| Cow::Borrowed(s)));
+            let mut close_list = false;
+            match stack.last_mut() {
+                Some(Frame::Top(Some(_))) => {
+                    let kind = match lexer.peek() {
+                        None => match stack.pop() {
+                            Some(Frame::Top(Some(value))) => return Ok(value),
+                            _ => unreachable!(),
+                        },
+                        Some(b) if is_closing_bracket(b as char) => SyntaxError::UnexpectedClose,
+                        Some(_) => SyntaxError::TrailingInput,
+                    };
+                    return Err(lexer.token_error(kind));
+                }
+                Some(Frame::List { open, vs, tail }) => {
+                    let close = closing_bracket(*open as char).map(|ch| ch as u8);
+                    let next = lexer.peek();
+                    let expects_tail = matches!(tail, Tail::Expected);
+                    if !expects_tail && next.is_some() && next == close {
+                        close_list = true;
+                    } else if !expects_tail && next.map_or(false, |b| is_closing_bracket(b as char))
+                    {
+                        return Err(lexer.token_error(SyntaxError::MismatchedBracket));
+                    } else if let Tail::Parsed(_) = tail {
+                        let kind = match next {
+                            None => SyntaxError::UnclosedList,
+                            Some(_) => SyntaxError::ExpectedClose,
+                        };
+                        return Err(lexer.token_error(kind));
+                    } else if matches!(tail, Tail::None) && !vs.is_empty() && lexer.is_dot() {
+                        *tail = Tail::Expected;
+                        lexer.bump(1);
+                        continue;
+                    } else if vs.len() >= limits.max_list_len && lexer.starts_value() {
+                        return Err(lexer.limit_error(Limit::ListLength(limits.max_list_len)));
+                    }
+                }
+                _ => {}
+            }
 
-named!(ws<CompleteStr, ()>, map!(take_while!(char::is_whitespace), |_| ()));
+            let value = if close_list {
+                lexer.bump(1);
+                match stack.pop() {
+                    Some(Frame::List { open, vs, tail }) => match tail {
+                        Tail::Parsed(tail) => Value::dotted_list(vs, tail),
+                        _ => match Bracket::from_open(open as char) {
+                            Some(bracket) if self.options.keep_brackets => {
+                                Value::Bracketed(bracket, vs)
+                            }
+                            _ => Value::List(vs),
+                        },
+                    },
+                    _ => unreachable!(),
+                }
+            } else {
+                match lexer.peek() {
+                    Some(open @ b'(') | Some(open @ b'[') | Some(open @ b'{') => {
+                        if depth >= limits.max_depth {
+                            return Err(lexer.limit_error(Limit::Depth(limits.max_depth)));
+                        }
+                        lexer.bump(1);
+                        stack.push(Frame::List {
+                            open,
+                            vs: Vec::new(),
+                            tail: Tail::None,
+                        });
+                        continue;
+                    }
+                    Some(b'\'') | Some(b'`') | Some(b',') => {
+                        if depth >= limits.max_depth {
+                            return Err(lexer.limit_error(Limit::Depth(limits.max_depth)));
+                        }
+                        if let Some(sym) = lexer.quote() {
+                            stack.push(Frame::Quote(sym));
+                        }
+                        continue;
+                    }
+                    _ => atom(lexer, limits, &stack)?,
+                }
+            };
 
-fn doesnt_need_quoting(ch: char) -> bool {
-    !needs_quoting(ch)
+            // Hand the finished value to the innermost unfinished one, finishing any quotes along
+            // the way.
+            let mut value = value;
+            loop {
+                match stack.last_mut() {
+                    Some(Frame::Top(top)) => *top = Some(value),
+                    Some(Frame::List {
+                        vs,
+                        tail: Tail::None,
+                        ..
+                    }) => vs.push(value),
+                    Some(Frame::List { tail, .. }) => *tail = Tail::Parsed(value),
+                    Some(Frame::Quote(sym)) => {
+                        value = Value::List(vec![Value::Sym(sym.to_string()), value]);
+                        let _ = stack.pop();
+                        continue;
+                    }
+                    Some(Frame::Comment(_)) => drop(stack.pop()),
+                    None => unreachable!(),
+                }
+                break;
+            }
+        }
+    }
+}
+
+/// Parses a symbol, escaped symbol or string literal, or fails with an appropriate error if the
+/// input does not continue with one.
+fn atom(lexer: &mut Lexer, limits: Limits, stack: &[Frame]) -> Result<Value, Error> {
+    let start = lexer.offset();
+    let mut s = String::new();
+    let quoted = matches!(lexer.peek(), Some(b'"') | Some(b'|'));
+    let r = match lexer.peek() {
+        None => {
+            let in_list = stack
+                .iter()
+                .any(|frame| matches!(frame, Frame::List { .. }));
+            let comment = stack.iter().rev().find_map(|frame| match frame {
+                Frame::Comment(start) => Some(*start),
+                _ => None,
+            });
+            return Err(match comment {
+                _ if in_list => lexer.token_error(SyntaxError::UnclosedList),
+                Some(start) => {
+                    let span = Span::at(lexer.input(), start, start + 2);
+                    Error::Syntax(SyntaxError::MissingDatum, span)
+                }
+                None => lexer.token_error(SyntaxError::Empty),
+            });
+        }
+        Some(b'"') => lexer.string(&mut s),
+        Some(b'|') => lexer.escaped_sym(&mut s),
+        Some(_) if lexer.is_dot() => return Err(lexer.token_error(SyntaxError::MisplacedDot)),
+        Some(_) => match lexer.symbol() {
+            Some(sym) => {
+                s.push_str(sym);
+                Ok(())
+            }
+            None => {
+                let ch = lexer.peek_char().unwrap();
+                let kind = if is_closing_bracket(ch) {
+                    SyntaxError::UnexpectedClose
+                } else {
+                    SyntaxError::UnexpectedChar(ch)
+                };
+                return Err(lexer.token_error(kind));
+            }
+        },
+    };
+
+    // A symbol that is too long is reported before any error after its first `max_symbol_len`
+    // bytes, since the streaming deserializer stops reading it there.
+    if s.len() > limits.max_symbol_len {
+        let input = lexer.input();
+        let end = start + input[start..].chars().next().map_or(0, char::len_utf8);
+        let span = Span::at(input, start, end);
+        let limit = Limit::SymbolLength(limits.max_symbol_len);
+        return Err(Error::LimitExceeded(limit, Some(span)));
+    }
+    r?;
+    if quoted {
+        Ok(Value::Str(s))
+    } else {
+        Ok(Value::bare_atom(s))
+    }
 }
@@ -0,0 +1,298 @@
+//! Sources of input for a `Deserializer`.
+
+use crate::{lexer::Chars, needs_quoting, Error, Limit, Position, Result, Span};
+use std::{io::BufRead, ops::Deref, str::from_utf8};
+
+/// A string that was either borrowed from the input, or copied into a scratch buffer.
+#[derive(Debug)]
+pub enum Reference<'b, 'c> {
+    /// A string borrowed from the input, which lives as long as the input does.
+    Borrowed(&'b str),
+
+    /// A string copied into a scratch buffer, which only lives until the next read.
+    Copied(&'c str),
+}
+
+impl<'b, 'c> Deref for Reference<'b, 'c> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Reference::Borrowed(s) => s,
+            Reference::Copied(s) => s,
+        }
+    }
+}
+
+/// A source of characters for a `Deserializer`.
+///
+/// This trait is sealed, and cannot be implemented outside of `serde_sexpr`.
+pub trait Read<'de>: private::Sealed {
+    /// Returns the next character without consuming it, or `None` at the end of the input.
+    #[doc(hidden)]
+    fn peek(&mut self) -> Result<Option<char>>;
+
+    /// Returns the character after the next one without consuming either, or `None` if the input
+    /// ends before it.
+    #[doc(hidden)]
+    fn peek2(&mut self) -> Result<Option<char>>;
+
+    /// Consumes the character last returned by `peek`.
+    #[doc(hidden)]
+    fn discard(&mut self);
+
+    /// Returns the position of the next character.
+    #[doc(hidden)]
+    fn position(&self) -> Position;
+
+    /// Limits the input to its first `max_len` bytes. Reading a character that ends after them
+    /// returns an error.
+    #[doc(hidden)]
+    fn set_max_len(&mut self, max_len: usize);
+
+    /// Parses an unescaped symbol, copying it into `scratch` if it cannot be borrowed. If the
+    /// symbol has to be copied, this stops once it is longer than `max_len` bytes.
+    #[doc(hidden)]
+    fn parse_symbol<'s>(
+        &'s mut self,
+        scratch: &'s mut String,
+        max_len: usize,
+    ) -> Result<Reference<'de, 's>> {
+        while let Some(ch) = self.peek()? {
+            if needs_quoting(ch) {
+                break;
+            }
+            scratch.push(ch);
+            self.discard();
+            if scratch.len() > max_len {
+                break;
+            }
+        }
+        Ok(Reference::Copied(scratch))
+    }
+}
+
+impl<'de, R: Read<'de>> Chars for R {
+    type Mark = Position;
+
+    fn peek(&mut self) -> Result<Option<char>> {
+        Read::peek(self)
+    }
+
+    fn peek2(&mut self) -> Result<Option<char>> {
+        Read::peek2(self)
+    }
+
+    fn discard(&mut self) {
+        Read::discard(self)
+    }
+
+    fn mark(&self) -> Position {
+        self.position()
+    }
+
+    fn span(&self, start: Position, end: Position) -> Span {
+        Span { start, end }
+    }
+}
+
+/// Returns the error for a character at the given position that ends after the first `max_len`
+/// bytes of the input.
+fn input_len_error(pos: Position, max_len: usize) -> Error {
+    let span = Span {
+        start: pos,
+        end: pos,
+    };
+    Error::LimitExceeded(Limit::InputLength(max_len), Some(span))
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl<'a> Sealed for super::StrRead<'a> {}
+    impl<R: std::io::BufRead> Sealed for super::IoRead<R> {}
+}
+
+/// A `Read` over a string.
+#[derive(Debug)]
+pub struct StrRead<'a> {
+    input: &'a str,
+    pos: Position,
+    max_len: Option<usize>,
+}
+
+impl<'a> StrRead<'a> {
+    /// Creates a new `StrRead` reading from the given string.
+    pub fn new(input: &'a str) -> StrRead<'a> {
+        StrRead {
+            input,
+            pos: Position::START,
+            max_len: None,
+        }
+    }
+
+    /// Returns the error for the end of the input if it has been limited, or the end of the
+    /// input otherwise.
+    fn end(&self, pos: Position) -> Result<Option<char>> {
+        match self.max_len {
+            Some(max_len) => Err(input_len_error(pos, max_len)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'a> Read<'a> for StrRead<'a> {
+    fn peek(&mut self) -> Result<Option<char>> {
+        match self.input[self.pos.offset..].chars().next() {
+            Some(ch) => Ok(Some(ch)),
+            None => self.end(self.pos),
+        }
+    }
+
+    fn peek2(&mut self) -> Result<Option<char>> {
+        let mut chars = self.input[self.pos.offset..].chars();
+        match (chars.next(), chars.next()) {
+            (_, Some(ch)) => Ok(Some(ch)),
+            (Some(ch), None) => {
+                let mut pos = self.pos;
+                pos.advance(ch);
+                self.end(pos)
+            }
+            (None, None) => Ok(None),
+        }
+    }
+
+    fn discard(&mut self) {
+        if let Some(ch) = self.input[self.pos.offset..].chars().next() {
+            self.pos.advance(ch);
+        }
+    }
+
+    fn position(&self) -> Position {
+        self.pos
+    }
+
+    fn set_max_len(&mut self, max_len: usize) {
+        if max_len < self.input.len() {
+            let mut end = max_len.max(self.pos.offset);
+            while !self.input.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.input = &self.input[..end];
+            self.max_len = Some(max_len);
+        }
+    }
+
+    fn parse_symbol<'s>(
+        &'s mut self,
+        _scratch: &'s mut String,
+        _max_len: usize,
+    ) -> Result<Reference<'a, 's>> {
+        let start = self.pos.offset;
+        let rest = &self.input[start..];
+        let s = &rest[..rest.find(needs_quoting).unwrap_or(rest.len())];
+        self.pos.offset += s.len();
+        self.pos.column += s.chars().count();
+        Ok(Reference::Borrowed(s))
+    }
+}
+
+/// A `Read` that pulls bytes from a `BufRead` as they are needed, decoding them as UTF-8.
+#[derive(Debug)]
+pub struct IoRead<R> {
+    reader: R,
+    peeked: Option<char>,
+    peeked2: Option<char>,
+    pos: Position,
+    read_len: usize,
+    max_len: usize,
+}
+
+impl<R: BufRead> IoRead<R> {
+    /// Creates a new `IoRead` reading from the given reader.
+    pub fn new(reader: R) -> IoRead<R> {
+        IoRead {
+            reader,
+            peeked: None,
+            peeked2: None,
+            pos: Position::START,
+            read_len: 0,
+            max_len: usize::MAX,
+        }
+    }
+
+    /// Reads and decodes a single character from the reader.
+    fn read_char(&mut self) -> Result<Option<char>> {
+        let first = match self.reader.fill_buf()?.first() {
+            Some(&b) => b,
+            None => return Ok(None),
+        };
+        let width = match first {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        if self.max_len - self.read_len < width {
+            let mut pos = self.pos;
+            for ch in self.peeked.iter().chain(&self.peeked2) {
+                pos.advance(*ch);
+            }
+            return Err(input_len_error(pos, self.max_len));
+        }
+        self.read_len += width;
+        if first < 0x80 {
+            self.reader.consume(1);
+            return Ok(Some(char::from(first)));
+        }
+
+        let mut bytes = [0; 4];
+        let mut len = 0;
+        while len < width {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            let n = buf.len().min(width - len);
+            bytes[len..len + n].copy_from_slice(&buf[..n]);
+            self.reader.consume(n);
+            len += n;
+        }
+        match from_utf8(&bytes[..len]) {
+            Ok(s) => Ok(s.chars().next()),
+            Err(err) => Err(Error::Utf8(err)),
+        }
+    }
+}
+
+impl<'de, R: BufRead> Read<'de> for IoRead<R> {
+    fn peek(&mut self) -> Result<Option<char>> {
+        if self.peeked.is_none() {
+            self.peeked = self.read_char()?;
+        }
+        Ok(self.peeked)
+    }
+
+    fn peek2(&mut self) -> Result<Option<char>> {
+        if Read::peek(self)?.is_some() && self.peeked2.is_none() {
+            self.peeked2 = self.read_char()?;
+        }
+        Ok(self.peeked2)
+    }
+
+    fn discard(&mut self) {
+        if let Some(ch) = self.peeked.take() {
+            self.pos.advance(ch);
+            self.peeked = self.peeked2.take();
+        }
+    }
+
+    fn position(&self) -> Position {
+        self.pos
+    }
+
+    fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len.max(self.read_len);
+    }
+}
@@ -1,9 +1,9 @@
-use crate::{Error, Result, Value};
+use crate::{Error, Limit, PrintOptions, Result, Value, DEFAULT_MAX_DEPTH};
 use serde::ser::{
     Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
     SerializeTupleStruct, SerializeTupleVariant,
 };
-use std::{fmt::Display, io::Write, str::from_utf8};
+use std::{convert::TryFrom, io::Write, str::from_utf8};
 
 /// Serialize the given data structure as an S-Expression into the writer.
 ///
@@ -14,11 +14,21 @@ use std::{fmt::Display, io::Write, str::from_utf8};
 /// let mut c = Cursor::new(Vec::new());
 /// let value = vec!["Hello!".to_string(), "Goodbye, world!".to_string(), ")|(".to_string()];
 /// serde_sexpr::to_writer(&mut c, &value).unwrap();
-/// let expected: &[u8] = b"(Hello! |Goodbye,\\ world!| |\\)\\|\\(|)";
+/// let expected: &[u8] = b"(|Hello!| |Goodbye,\\ world!| |\\)\\|\\(|)";
 /// assert_eq!(c.into_inner(), expected);
 /// ```
-pub fn to_writer<T: Serialize + ?Sized, W: Write>(mut writer: W, value: &T) -> Result<()> {
-    let bytes = to_vec(value)?;
+pub fn to_writer<T: Serialize + ?Sized, W: Write>(writer: W, value: &T) -> Result<()> {
+    to_writer_with(writer, value, PrintOptions::default())
+}
+
+/// Serialize the given data structure as an S-Expression into the writer, printing it with the
+/// given options.
+pub fn to_writer_with<T: Serialize + ?Sized, W: Write>(
+    mut writer: W,
+    value: &T,
+    options: PrintOptions,
+) -> Result<()> {
+    let bytes = to_vec_with(value, options)?;
     writer.write_all(&bytes)?;
     Ok(())
 }
@@ -31,11 +41,17 @@ pub fn to_writer<T: Serialize + ?Sized, W: Write>(mut writer: W, value: &T) -> R
 /// let value = vec!["Hello!".to_string(), "Goodbye, world!".to_string(), ")|(".to_string()];
 /// assert_eq!(
 ///     serde_sexpr::to_vec(&value).unwrap(),
-///     "(Hello! |Goodbye,\\ world!| |\\)\\|\\(|)".as_bytes(),
+///     "(|Hello!| |Goodbye,\\ world!| |\\)\\|\\(|)".as_bytes(),
 /// );
 /// ```
 pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
-    to_string(value).map(String::into_bytes)
+    to_vec_with(value, PrintOptions::default())
+}
+
+/// Serialize the given data structure as an S-Expression in a byte vector, printing it with the
+/// given options.
+pub fn to_vec_with<T: Serialize + ?Sized>(value: &T, options: PrintOptions) -> Result<Vec<u8>> {
+    to_string_with(value, options).map(String::into_bytes)
 }
 
 /// Serialize the given data structure as an S-Expression in a string.
@@ -46,15 +62,39 @@ pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
 /// let value = vec!["Hello!".to_string(), "Goodbye, world!".to_string(), ")|(".to_string()];
 /// assert_eq!(
 ///     serde_sexpr::to_string(&value).unwrap(),
-///     "(Hello! |Goodbye,\\ world!| |\\)\\|\\(|)",
+///     "(|Hello!| |Goodbye,\\ world!| |\\)\\|\\(|)",
 /// );
 /// ```
 pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
-    to_value(value).map(|v| v.to_string())
+    to_string_with(value, PrintOptions::default())
+}
+
+/// Serialize the given data structure as an S-Expression in a string, printing it with the given
+/// options.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_sexpr::{PrintOptions, StringStyle};
+/// let value = vec!["Hello!".to_string(), "Goodbye, world!".to_string(), ")|(".to_string()];
+/// let options = PrintOptions {
+///     string_style: StringStyle::Quoted,
+///     ..PrintOptions::default()
+/// };
+/// assert_eq!(
+///     serde_sexpr::to_string_with(&value, options).unwrap(),
+///     "(\"Hello!\" \"Goodbye, world!\" \")|(\")",
+/// );
+/// ```
+pub fn to_string_with<T: Serialize + ?Sized>(value: &T, options: PrintOptions) -> Result<String> {
+    to_value(value).map(|v| v.display(options).to_string())
 }
 
 /// Serialize the given data structure as an S-Expression in a `serde_sexpr::Value`.
 ///
+/// Returns an error if the S-Expression would have lists nested more than `DEFAULT_MAX_DEPTH`
+/// deep.
+///
 /// # Examples
 ///
 /// ```
@@ -65,16 +105,115 @@ pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
 /// );
 /// ```
 pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
-    value.serialize(Serializer)
+    value.serialize(Serializer::new())
 }
 
-/// A serializer for S-Expressions.
-#[derive(Debug)]
-pub struct Serializer;
+/// How an `Option` is written as an S-Expression.
+///
+/// With the default, `Transparent`, `Some(x)` is written as `x` and `None` as `()`, so `None`
+/// cannot be told apart from `Some(())`, `Some(vec![])` or `Some(None)`. The other encodings
+/// are unambiguous, so that an `Option<Option<T>>` can be round-tripped.
+///
+/// The same encoding must be used to serialize and deserialize a value. It is set with
+/// `Serializer::set_option_encoding`, `Deserializer::set_option_encoding` and
+/// `ValueDeserializer::set_option_encoding`.
+///
+/// # Examples
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// # use serde_sexpr::{sexpr, OptionEncoding, Serializer, ValueDeserializer};
+/// let value: Vec<Option<Option<u32>>> = vec![Some(Some(1)), Some(None), None];
+///
+/// let mut ser = Serializer::new();
+/// ser.set_option_encoding(OptionEncoding::Wrapped);
+/// let wrapped = value.serialize(ser).unwrap();
+/// assert_eq!(wrapped, sexpr!((((1)) (()) ())));
+///
+/// let mut ser = Serializer::new();
+/// ser.set_option_encoding(OptionEncoding::Tagged);
+/// let tagged = value.serialize(ser).unwrap();
+/// assert_eq!(tagged, sexpr!(((some (some 1)) (some none) none)));
+///
+/// let mut de = ValueDeserializer::new(tagged);
+/// de.set_option_encoding(OptionEncoding::Tagged);
+/// assert_eq!(Vec::<Option<Option<u32>>>::deserialize(de).unwrap(), value);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum OptionEncoding {
+    /// `Some(x)` is written as `x`, and `None` as `()`.
+    #[default]
+    Transparent,
+
+    /// `Some(x)` is written as the one-element list `(x)`, and `None` as `()`, as in OCaml's
+    /// sexplib.
+    Wrapped,
+
+    /// `Some(x)` is written as `(some x)`, and `None` as the symbol `none`.
+    Tagged,
+}
 
-/// Serializes a `Display`.
-fn serialize_generic<T: Display>(value: T) -> Result<Value> {
-    Ok(Value::Sym(value.to_string()))
+/// A serializer for S-Expressions, which produces a `Value`.
+///
+/// `Serializer::new()` creates one with the default settings.
+///
+/// # Examples
+///
+/// ```
+/// # use serde::Serialize;
+/// # use serde_sexpr::{sexpr, Error, Limit, Serializer};
+/// let value = vec![vec![1, 2], vec![3]];
+/// assert_eq!(value.serialize(Serializer::new()).unwrap(), sexpr!(((1 2) (3))));
+/// assert!(value.serialize(Serializer::with_max_depth(2)).is_ok());
+/// match value.serialize(Serializer::with_max_depth(1)) {
+///     Err(Error::LimitExceeded(Limit::Depth(1), None)) => {}
+///     r => panic!("{:?}", r),
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Serializer {
+    depth: usize,
+    max_depth: usize,
+    option_encoding: OptionEncoding,
+}
+
+impl Serializer {
+    /// Creates a serializer that allows lists to be nested `DEFAULT_MAX_DEPTH` deep.
+    pub fn new() -> Serializer {
+        Serializer::with_max_depth(DEFAULT_MAX_DEPTH)
+    }
+
+    /// Creates a serializer that allows lists to be nested `max_depth` deep.
+    pub fn with_max_depth(max_depth: usize) -> Serializer {
+        Serializer {
+            depth: 0,
+            max_depth,
+            option_encoding: OptionEncoding::default(),
+        }
+    }
+
+    /// Sets how `Option`s are written, which is `OptionEncoding::Transparent` by default.
+    pub fn set_option_encoding(&mut self, option_encoding: OptionEncoding) {
+        self.option_encoding = option_encoding;
+    }
+
+    /// Returns the serializer for the elements of a list, or an error if the list would be nested
+    /// too deeply.
+    fn nested(self) -> Result<Serializer> {
+        if self.depth >= self.max_depth {
+            return Err(Error::LimitExceeded(Limit::Depth(self.max_depth), None));
+        }
+        Ok(Serializer {
+            depth: self.depth + 1,
+            ..self
+        })
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Serializer {
+        Serializer::new()
+    }
 }
 
 impl serde::ser::Serializer for Serializer {
@@ -90,75 +229,102 @@ impl serde::ser::Serializer for Serializer {
     type SerializeStructVariant = MapSerializer;
 
     fn serialize_bool(self, v: bool) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Bool(v))
     }
 
     fn serialize_i8(self, v: i8) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Int(v.into()))
     }
 
     fn serialize_i16(self, v: i16) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Int(v.into()))
     }
 
     fn serialize_i32(self, v: i32) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Int(v.into()))
     }
 
     fn serialize_i64(self, v: i64) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value> {
+        Ok(Value::Int(v))
     }
 
     fn serialize_u8(self, v: u8) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Int(v.into()))
     }
 
     fn serialize_u16(self, v: u16) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Int(v.into()))
     }
 
     fn serialize_u32(self, v: u32) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Int(v.into()))
     }
 
     fn serialize_u64(self, v: u64) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value> {
+        Ok(i128::try_from(v).map_or_else(|_| Value::Sym(v.to_string()), Value::Int))
     }
 
     fn serialize_f32(self, v: f32) -> Result<Value> {
-        serialize_generic(v)
+        // Going through the shortest decimal representation of the `f32` keeps it from being
+        // printed with the extra digits of the nearest `f64`.
+        let v = v.to_string().parse().unwrap_or_else(|_| v.into());
+        Ok(Value::Float(v))
     }
 
     fn serialize_f64(self, v: f64) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Float(v))
     }
 
     fn serialize_char(self, v: char) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Str(v.to_string()))
     }
 
     fn serialize_str(self, v: &str) -> Result<Value> {
-        serialize_generic(v)
+        Ok(Value::Str(v.to_string()))
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
         let v = from_utf8(v)?;
-        serialize_generic(v)
+        Ok(Value::Str(v.to_string()))
     }
 
     fn serialize_none(self) -> Result<Value> {
-        Ok(sexpr!(()))
+        match self.option_encoding {
+            OptionEncoding::Transparent | OptionEncoding::Wrapped => {
+                let _ = self.nested()?;
+                Ok(sexpr!(()))
+            }
+            OptionEncoding::Tagged => Ok(sexpr!(none)),
+        }
     }
 
     fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value> {
-        value.serialize(self)
+        match self.option_encoding {
+            OptionEncoding::Transparent => value.serialize(self),
+            OptionEncoding::Wrapped => value
+                .serialize(self.nested()?)
+                .map(|value| Value::List(vec![value])),
+            OptionEncoding::Tagged => value
+                .serialize(self.nested()?)
+                .map(|value| Value::List(vec![sexpr!(some), value])),
+        }
     }
 
     fn serialize_unit(self) -> Result<Value> {
+        let _ = self.nested()?;
         Ok(sexpr!(()))
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        let _ = self.nested()?;
         Ok(sexpr!(()))
     }
 
@@ -187,12 +353,12 @@ impl serde::ser::Serializer for Serializer {
         value: &T,
     ) -> Result<Value> {
         value
-            .serialize(self)
+            .serialize(self.nested()?)
             .map(|value| Value::List(vec![Value::Sym(variant.to_string()), value]))
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        Ok(ListSerializer(Vec::new()))
+        Ok(ListSerializer(self.nested()?, Vec::new()))
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
@@ -214,11 +380,14 @@ impl serde::ser::Serializer for Serializer {
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        Ok(ListSerializer(vec![Value::Sym(variant.to_string())]))
+        Ok(ListSerializer(
+            self.nested()?,
+            vec![Value::Sym(variant.to_string())],
+        ))
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        Ok(MapSerializer(Vec::new(), None))
+        Ok(MapSerializer(self.nested()?, Vec::new(), None))
     }
 
     fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
@@ -232,23 +401,27 @@ impl serde::ser::Serializer for Serializer {
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        Ok(MapSerializer(vec![Value::Sym(variant.to_string())], None))
+        Ok(MapSerializer(
+            self.nested()?,
+            vec![Value::Sym(variant.to_string())],
+            None,
+        ))
     }
 }
 
 #[derive(Debug)]
-pub struct ListSerializer(Vec<Value>);
+pub struct ListSerializer(Serializer, Vec<Value>);
 
 impl SerializeSeq for ListSerializer {
     type Ok = Value;
     type Error = Error;
 
     fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
-        to_value(value).map(|v| self.0.push(v))
+        value.serialize(self.0).map(|v| self.1.push(v))
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::List(self.0))
+        Ok(Value::List(self.1))
     }
 }
 
@@ -257,11 +430,11 @@ impl SerializeTuple for ListSerializer {
     type Error = Error;
 
     fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
-        to_value(value).map(|v| self.0.push(v))
+        value.serialize(self.0).map(|v| self.1.push(v))
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::List(self.0))
+        Ok(Value::List(self.1))
     }
 }
 
@@ -270,11 +443,11 @@ impl SerializeTupleStruct for ListSerializer {
     type Error = Error;
 
     fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
-        to_value(value).map(|v| self.0.push(v))
+        value.serialize(self.0).map(|v| self.1.push(v))
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::List(self.0))
+        Ok(Value::List(self.1))
     }
 }
 
@@ -283,37 +456,38 @@ impl SerializeTupleVariant for ListSerializer {
     type Error = Error;
 
     fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
-        to_value(value).map(|v| self.0.push(v))
+        value.serialize(self.0).map(|v| self.1.push(v))
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::List(self.0))
+        Ok(Value::List(self.1))
     }
 }
 
 #[derive(Debug)]
-pub struct MapSerializer(Vec<Value>, Option<Value>);
+pub struct MapSerializer(Serializer, Vec<Value>, Option<Value>);
 
 impl SerializeMap for MapSerializer {
     type Ok = Value;
     type Error = Error;
 
     fn serialize_key<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
-        debug_assert!(self.1.is_none());
-        self.1 = Some(to_value(value)?);
+        debug_assert!(self.2.is_none());
+        // The key and value are elements of a `(key value)` list, which is a level of nesting.
+        self.2 = Some(value.serialize(self.0.nested()?)?);
         Ok(())
     }
 
     fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
-        debug_assert!(self.1.is_some());
-        to_value(value).map(|v| {
-            self.0.push(Value::List(vec![self.1.take().unwrap(), v]));
+        debug_assert!(self.2.is_some());
+        value.serialize(self.0.nested()?).map(|v| {
+            self.1.push(Value::List(vec![self.2.take().unwrap(), v]));
         })
     }
 
     fn end(self) -> Result<Value> {
-        debug_assert!(self.1.is_none());
-        Ok(Value::List(self.0))
+        debug_assert!(self.2.is_none());
+        Ok(Value::List(self.1))
     }
 }
 
@@ -326,13 +500,14 @@ impl SerializeStruct for MapSerializer {
         key: &'static str,
         value: &T,
     ) -> Result<()> {
-        self.serialize_key(key)?;
-        self.serialize_value(value)
+        debug_assert!(self.2.is_none());
+        self.2 = Some(Value::Sym(key.to_string()));
+        SerializeMap::serialize_value(self, value)
     }
 
     fn end(self) -> Result<Value> {
-        debug_assert!(self.1.is_none());
-        Ok(Value::List(self.0))
+        debug_assert!(self.2.is_none());
+        Ok(Value::List(self.1))
     }
 }
 
@@ -345,12 +520,13 @@ impl SerializeStructVariant for MapSerializer {
         key: &'static str,
         value: &T,
     ) -> Result<()> {
-        self.serialize_key(key)?;
+        debug_assert!(self.2.is_none());
+        self.2 = Some(Value::Sym(key.to_string()));
         self.serialize_value(value)
     }
 
     fn end(self) -> Result<Value> {
-        debug_assert!(self.1.is_none());
-        Ok(Value::List(self.0))
+        debug_assert!(self.2.is_none());
+        Ok(Value::List(self.1))
     }
 }
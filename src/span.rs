@@ -0,0 +1,94 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A position in the input text.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Position {
+    /// The offset from the start of the input, in bytes.
+    pub offset: usize,
+
+    /// The line number, starting at 1.
+    pub line: usize,
+
+    /// The column number, in characters, starting at 1.
+    pub column: usize,
+}
+
+impl Position {
+    /// Computes the position of the given byte offset in the input. An offset past the end of the
+    /// input is moved back to the end, and one inside a character is moved back to its start.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_sexpr::Position;
+    /// let pos = Position::at("(foo\n  bar)", 7);
+    /// assert_eq!(pos, Position { offset: 7, line: 2, column: 3 });
+    ///
+    /// assert_eq!(Position::at("(\u{e9})", 2).offset, 1);
+    /// assert_eq!(Position::at("(foo)", 10).offset, 5);
+    /// ```
+    pub fn at(input: &str, offset: usize) -> Position {
+        let mut offset = offset.min(input.len());
+        while !input.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let before = &input[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Position {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    /// The position of the start of the input.
+    pub(crate) const START: Position = Position {
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+
+    /// Advances the position past the given character.
+    pub(crate) fn advance(&mut self, ch: char) {
+        self.offset += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// A region of the input text, from `start` (inclusive) to `end` (exclusive).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Span {
+    /// The position of the first character in the span.
+    pub start: Position,
+
+    /// The position just past the last character in the span.
+    pub end: Position,
+}
+
+impl Span {
+    /// Computes the span between the given byte offsets in the input. The offsets are moved back
+    /// onto the input as `Position::at` moves them.
+    pub fn at(input: &str, start: usize, end: usize) -> Span {
+        Span {
+            start: Position::at(input, start),
+            end: Position::at(input, end),
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        self.start.fmt(fmt)
+    }
+}
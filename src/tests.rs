@@ -1,14 +1,322 @@
-use crate::Value;
+use crate::{
+    from_reader, from_reader_with, from_str, from_str_with, from_value, from_value_with,
+    read::Read, to_string, to_value, Atom, Bracket, Deserializer, Document, Error, Event,
+    EventReader, Lexer, Limit, Limits, Node, OptionEncoding, ParseOptions, PrintOptions,
+    Serializer, StringStyle, SyntaxError, TokenKind, Value, ValueDeserializer, DEFAULT_MAX_DEPTH,
+};
 use proptest::prelude::*;
+use serde::{
+    de::{IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Serialize,
+};
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    fmt::{Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+    io::BufReader,
+};
+
+/// A type whose values are ambiguous with `OptionEncoding::Transparent`.
+type NestedOptions = Vec<Option<Option<Option<Vec<Option<()>>>>>>;
 
 proptest! {
     #[test]
     fn parse_of_to_string(v: Value) {
         let s = dbg!(v.to_string());
-        let r = s.parse::<Value>();
+        prop_assert!(!s.contains(|ch: char| ch.is_control() || (ch.is_whitespace() && ch != ' ')));
+        let options = ParseOptions {
+            keep_brackets: true,
+            ..ParseOptions::default()
+        };
+        let r = Value::parse_with(&s, options);
         prop_assert!(r.is_ok());
         let v2 = r.unwrap();
-        prop_assert_eq!(v, v2);
+        prop_assert_eq!(reparsed(&v, PrintOptions::default()), v2);
+    }
+
+    #[test]
+    fn parse_of_quoted_display(v: Value) {
+        let options = PrintOptions {
+            string_style: StringStyle::Quoted,
+            quote_shorthand: true,
+            ..PrintOptions::default()
+        };
+        let s = v.display(options).to_string();
+        let expected = reparsed(&v, options);
+        let options = ParseOptions {
+            keep_brackets: true,
+            ..ParseOptions::default()
+        };
+        prop_assert_eq!(expected, Value::parse_with(&s, options).unwrap());
+    }
+
+    #[test]
+    fn from_reader_of_to_string(v: BTreeMap<String, (Option<char>, Vec<i64>, ())>) {
+        let s = to_string(&v).unwrap();
+        let r = from_reader(BufReader::with_capacity(1, s.as_bytes()));
+        prop_assert_eq!(v, r.unwrap());
+    }
+
+    #[test]
+    fn nested_options_round_trip(v: NestedOptions, tagged: bool) {
+        let encoding = if tagged {
+            OptionEncoding::Tagged
+        } else {
+            OptionEncoding::Wrapped
+        };
+        let mut ser = Serializer::new();
+        ser.set_option_encoding(encoding);
+        let value = v.serialize(ser).unwrap();
+        let s = value.to_string();
+
+        let mut de = ValueDeserializer::new(value);
+        de.set_option_encoding(encoding);
+        let v2: NestedOptions = Deserialize::deserialize(de).unwrap();
+        prop_assert_eq!(&v2, &v);
+
+        let mut de = Deserializer::from_str(&s);
+        de.set_option_encoding(encoding);
+        let v2: NestedOptions =
+            Deserialize::deserialize(&mut de).unwrap();
+        de.end().unwrap();
+        prop_assert_eq!(v2, v);
+    }
+
+    #[test]
+    fn parse_recovering_agrees(s in "([()\\[\\]{}.'`,@#;|\\\\\" \n\u{a0}ab]|\\\\q){0,24}", small: bool) {
+        let limits = if small {
+            Limits {
+                max_depth: 3,
+                max_list_len: 3,
+                max_symbol_len: 3,
+                max_input_len: 20,
+            }
+        } else {
+            Limits::default()
+        };
+        let options = ParseOptions {
+            keep_brackets: true,
+            limits,
+        };
+        let (doc, errors) = Document::parse_recovering(&s, options);
+        let (value, _) = Value::parse_recovering(&s, options);
+        match Value::parse_with(&s, options) {
+            Ok(expected) => {
+                prop_assert!(errors.is_empty());
+                prop_assert_eq!(value, Some(expected));
+            }
+            Err(err) => prop_assert_eq!(errors[0].to_string(), err.to_string()),
+        }
+        if s.len() <= limits.max_input_len {
+            prop_assert_eq!(doc.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn document_agrees(
+        s in prop_oneof![
+            "([()\\[\\]{}.'`,@#;|\\\\\" \n\u{a0}ab:1]|\\\\[qu{]){0,32}",
+            any::<String>(),
+        ],
+        small: bool,
+    ) {
+        let limits = if small {
+            Limits {
+                max_depth: 3,
+                max_list_len: 3,
+                max_symbol_len: 3,
+                max_input_len: 20,
+            }
+        } else {
+            Limits::default()
+        };
+        let options = ParseOptions {
+            keep_brackets: false,
+            limits,
+        };
+        match (Document::parse_with(&s, options), Value::parse_with(&s, options)) {
+            (Ok(doc), Ok(value)) => prop_assert_eq!(doc.root().to_value(), value),
+            (Err(doc_err), Err(err)) => {
+                prop_assert_eq!(format!("{:?}", doc_err), format!("{:?}", err))
+            }
+            (doc, value) => prop_assert!(false, "{:?} != {:?}", doc.map(|doc| doc.to_string()), value),
+        }
+    }
+
+    #[test]
+    fn event_reader_agrees(s in "([()\\[\\]{}.'`,@#;|\\\\\" \n\u{a0}ab]|\\\\q){0,24}", small: bool) {
+        let limits = if small {
+            Limits {
+                max_depth: 3,
+                max_list_len: 3,
+                max_symbol_len: 3,
+                ..Limits::default()
+            }
+        } else {
+            Limits::default()
+        };
+        let options = ParseOptions {
+            keep_brackets: false,
+            limits,
+        };
+        let expected = Value::parse_with(&s, options);
+        let mut from_str = EventReader::from_str(&s);
+        from_str.set_limits(limits);
+        let mut from_reader = EventReader::from_reader(BufReader::with_capacity(1, s.as_bytes()));
+        from_reader.set_limits(limits);
+        for value in [read_first_value(&mut from_str), read_first_value(&mut from_reader)] {
+            match (&expected, value) {
+                (Ok(expected), value) => prop_assert_eq!(value.ok(), Some(Some(expected.clone()))),
+                (Err(expected), Err(err)) => prop_assert_eq!(err.to_string(), expected.to_string()),
+                (Err(_), Ok(_)) => {}
+            }
+        }
+    }
+}
+
+/// Returns the value that printing `v` with the given options and parsing it again produces. This
+/// is `v` itself, except that symbols and keywords that have to be printed quoted come back as
+/// strings, and dotted lists come back as the parser makes them: a dotted list with no elements as
+/// its tail, and one whose tail is a list merged with it.
+fn reparsed(v: &Value, options: PrintOptions) -> Value {
+    let list = |vs: &[Value]| vs.iter().map(|v| reparsed(v, options)).collect();
+    match v {
+        Value::List(vs) => Value::List(list(vs)),
+        Value::Bracketed(bracket, vs) => Value::Bracketed(*bracket, list(vs)),
+        Value::DottedList(vs, tail) if vs.is_empty() => reparsed(tail, options),
+        Value::DottedList(vs, tail) => Value::dotted_list(list(vs), reparsed(tail, options)),
+        Value::Sym(_) | Value::Keyword(_) => {
+            let s = v.display(options).to_string();
+            if s.starts_with(['|', '"']) {
+                s.parse().unwrap()
+            } else {
+                v.clone()
+            }
+        }
+        _ => v.clone(),
+    }
+}
+
+/// Reads the events of the first top-level value from `reader`, and builds the value they stand
+/// for. Returns `None` if the input has no values.
+fn read_first_value<'de, R: Read<'de>>(
+    reader: &mut EventReader<R>,
+) -> Result<Option<Value>, Error> {
+    let mut lists = Vec::new();
+    while let Some((_, event)) = reader.next_event()? {
+        let value = match event {
+            Event::StartList => {
+                lists.push((Vec::new(), false));
+                continue;
+            }
+            Event::Dot => {
+                lists.last_mut().unwrap().1 = true;
+                continue;
+            }
+            Event::EndList => match lists.pop().unwrap() {
+                (mut vs, true) => {
+                    let tail = vs.pop().unwrap();
+                    Value::dotted_list(vs, tail)
+                }
+                (vs, false) => Value::List(vs),
+            },
+            Event::Atom(s) => Value::bare_atom(s.to_string()),
+            Event::Str(s) => Value::Str(s.to_string()),
+        };
+        match lists.last_mut() {
+            Some((vs, _)) => vs.push(value),
+            None => return Ok(Some(value)),
+        }
+    }
+    Ok(None)
+}
+
+/// The depth of the values used to test that `Value`'s trait implementations do not recurse.
+const DEEP: usize = 1_000_000;
+
+/// Builds a value nested `depth` lists deep around the given symbol, using the kinds of list given
+/// by `kinds` in turn.
+fn deep_value(kinds: &[u8], depth: usize, leaf: &str) -> Value {
+    let mut value = Value::Sym(leaf.to_string());
+    for &kind in kinds.iter().cycle().take(depth) {
+        value = match kind {
+            0 => Value::List(vec![value]),
+            1 => Value::Bracketed(Bracket::Square, vec![Value::Sym("x".to_string()), value]),
+            _ => Value::DottedList(vec![Value::Sym("x".to_string())], Box::new(value)),
+        };
+    }
+    value
+}
+
+/// Checks that cloning, comparing, hashing and printing values nested `depth` lists deep around
+/// the symbols `a` and `b` agree with comparing the symbols. The values are dropped at the end.
+fn check_deep_values(kinds: &[u8], depth: usize, a: &str, b: &str) {
+    let v = deep_value(kinds, depth, a);
+    let w = v.clone();
+    assert!(v == w);
+    assert_eq!(v.cmp(&w), Ordering::Equal);
+    let hash = |v: &Value| {
+        let mut hasher = DefaultHasher::new();
+        v.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&v), hash(&w));
+
+    let w = deep_value(kinds, depth, b);
+    assert_eq!(v == w, a == b);
+    assert_eq!(v.cmp(&w), a.cmp(b));
+
+    let s = v.to_string();
+    assert_eq!(s.matches(['(', '[']).count(), depth);
+    assert_eq!(s.matches([')', ']']).count(), depth);
+    assert!(s.contains(a));
+}
+
+#[test]
+fn deep_values() {
+    check_deep_values(&[0, 1, 2], DEEP, "a", "b");
+}
+
+#[test]
+fn deep_documents() {
+    let depth = DEEP / 5;
+    let value = Value::List(vec![
+        Value::Sym("quote".to_string()),
+        deep_value(&[0, 1, 2], depth, "a"),
+    ]);
+    let node = Node::new(&value).unwrap();
+    let copy = node.clone();
+    assert!(node == copy);
+    let hash = |node: &Node| {
+        let mut hasher = DefaultHasher::new();
+        node.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&node), hash(&copy));
+
+    let s = node.to_string();
+    assert_eq!(s, value.to_string());
+    let options = ParseOptions {
+        keep_brackets: false,
+        limits: Limits {
+            max_depth: usize::MAX,
+            ..Limits::default()
+        },
+    };
+    assert_eq!(node.to_value(), Value::parse_with(&s, options).unwrap());
+
+    let mut other = Node::atom("b");
+    other
+        .set_value(&deep_value(&[0, 1, 2], depth, "b"))
+        .unwrap();
+    assert!(node != other);
+}
+
+proptest! {
+    #[test]
+    fn nested_values(kinds in prop::collection::vec(0..3u8, 1..8), a in "[a-z]{1,8}", b in "[a-z]{1,8}") {
+        check_deep_values(&kinds, 100, &a, &b);
     }
 }
 
@@ -35,16 +343,1000 @@ impl Arbitrary for Value {
 
     fn arbitrary_with(params: ValueArbitraryParams) -> Self::Strategy {
         let max_collection_size = params.max_collection_size as usize;
-        any::<String>()
-            .prop_map(Value::Sym)
-            .prop_recursive(
-                params.depth,
-                params.max_size,
-                params.max_collection_size,
-                move |inner| {
-                    prop::collection::vec(inner, 0..max_collection_size).prop_map(Value::List)
+        prop_oneof![
+            any::<String>().prop_map(Value::Sym),
+            any::<String>().prop_map(Value::Str),
+            any::<String>().prop_map(Value::Keyword),
+            any::<i128>().prop_map(Value::Int),
+            any::<f64>().prop_map(Value::Float),
+            any::<bool>().prop_map(Value::Bool),
+        ]
+        .prop_recursive(
+            params.depth,
+            params.max_size,
+            params.max_collection_size,
+            move |inner| {
+                let list = prop::collection::vec(inner.clone(), 0..max_collection_size);
+                let bracket = prop_oneof![Just(Bracket::Square), Just(Bracket::Curly)];
+                prop_oneof![
+                    list.clone().prop_map(Value::List),
+                    (bracket, list.clone()).prop_map(|(bracket, vs)| Value::Bracketed(bracket, vs)),
+                    (list, inner).prop_map(|(vs, tail)| Value::DottedList(vs, Box::new(tail))),
+                ]
+            },
+        )
+        .boxed()
+    }
+}
+
+#[test]
+fn parse_error_spans() {
+    let span = |s: &str| s.parse::<Value>().unwrap_err().span().unwrap();
+
+    let s = span("(foo\n  (bar baz)");
+    assert_eq!((s.start.offset, s.start.line, s.start.column), (16, 2, 12));
+    assert_eq!(s.end.offset, 16);
+
+    let s = span("(foo |bar\\ baz");
+    assert_eq!((s.start.offset, s.start.line, s.start.column), (14, 1, 15));
+
+    let s = span("foo bar");
+    assert_eq!((s.start.offset, s.end.offset), (4, 7));
+}
+
+#[test]
+fn parse_error_kinds() {
+    let kind = |s: &str| match s.parse::<Value>() {
+        Err(Error::Syntax(kind, _)) => kind,
+        r => panic!("{:?} parsed to {:?}", s, r),
+    };
+
+    assert_eq!(kind(""), SyntaxError::Empty);
+    assert_eq!(kind("  \n"), SyntaxError::Empty);
+    assert_eq!(kind("(foo (bar)"), SyntaxError::UnclosedList);
+    assert_eq!(kind(")"), SyntaxError::UnexpectedClose);
+    assert_eq!(kind("(foo))"), SyntaxError::UnexpectedClose);
+    assert_eq!(kind("(foo |bar"), SyntaxError::UnterminatedEscapedSymbol);
+    assert_eq!(kind("(foo |bar)"), SyntaxError::UnexpectedChar(')'));
+    assert_eq!(kind("|foo\\"), SyntaxError::DanglingBackslash);
+    assert_eq!(kind("|foo bar|"), SyntaxError::UnexpectedChar(' '));
+    assert_eq!(kind("foo bar"), SyntaxError::TrailingInput);
+    assert_eq!(kind("\"foo"), SyntaxError::UnterminatedString);
+    assert_eq!(kind("\"foo\\x\""), SyntaxError::InvalidEscape);
+    assert_eq!(kind("(. foo)"), SyntaxError::MisplacedDot);
+    assert_eq!(kind("(foo . bar baz)"), SyntaxError::ExpectedClose);
+    assert_eq!(kind("(foo]"), SyntaxError::MismatchedBracket);
+    assert_eq!(kind("[foo}"), SyntaxError::MismatchedBracket);
+    assert_eq!(kind("]"), SyntaxError::UnexpectedClose);
+    assert_eq!(kind("#;"), SyntaxError::MissingDatum);
+    assert_eq!(kind("'#; #;a"), SyntaxError::MissingDatum);
+    assert_eq!(kind("(foo #;"), SyntaxError::UnclosedList);
+
+    // A datum comment with nothing after it is reported at the `#;`, by every parser.
+    let s = "foo #;#; ; bar\n";
+    let span = s.parse::<Value>().unwrap_err().span().unwrap();
+    assert_eq!((span.start.offset, span.end.offset), (6, 8));
+    let errors = [
+        s.parse::<Document>().unwrap_err(),
+        from_str::<IgnoredAny>(s).unwrap_err(),
+        from_reader::<_, IgnoredAny>(s.as_bytes()).unwrap_err(),
+    ];
+    for err in &errors {
+        match err {
+            Error::Syntax(SyntaxError::MissingDatum, found) => assert_eq!(*found, span),
+            err => panic!("expected a missing datum, got {:?}", err),
+        }
+    }
+}
+
+#[test]
+fn stream_recovers_from_type_errors_only() {
+    let mut stream = Deserializer::from_str("1 two 3 (4").into_iter::<u32>();
+    assert_eq!(stream.next().unwrap().unwrap(), 1);
+    assert!(stream.next().unwrap().is_err());
+    assert_eq!(stream.next().unwrap().unwrap(), 3);
+    assert_eq!(stream.byte_offset(), 7);
+    match stream.next() {
+        Some(Err(Error::Syntax(SyntaxError::UnclosedList, _))) => {}
+        r => panic!("expected an unclosed list, got {:?}", r),
+    }
+    assert!(stream.next().is_none());
+    assert_eq!(stream.byte_offset(), 7);
+}
+
+#[test]
+fn deserializers_agree_on_errors() {
+    let cases = [
+        "",
+        "(1 2",
+        "(1 2))",
+        "(1 |2",
+        "(1 |2\\",
+        "(1 |2 3|)",
+        "(1 2) 3",
+        "(1 (2))",
+        "(1 \u{1f600})",
+        "(1 \"2",
+        "(1 \"2\\q\")",
+        "(1 \"2\\u{110000}\")",
+        "(1 \"2\\u{12",
+        "(1 \"2\\",
+        ".",
+        "(1 (. 2))",
+        "(1 (2 . ))",
+        "(1 (2 . 3 4))",
+        "(1 (2 . 3",
+        "(1 (2 . . 3))",
+        "(1 ')",
+        "(1 '",
+        "(1 '.)",
+        "(1 ,@ )",
+        "(1 'x)",
+        "(1 2) 'x",
+        "(1 2]",
+        "[1 2)",
+        "(1 [2 3)",
+        "(1 {2 . 3]",
+        "(1 2)]",
+        "(1 [2 3",
+        "(1 2) #;",
+        "(1 2) #; #;3",
+        "(1 2) #;'",
+    ];
+    let deep = format!("(1 {}2{})", "(".repeat(200), ")".repeat(200));
+    let quoted = format!("(1 {}2)", "'".repeat(200));
+    let commented = format!("(1 {}2 3)", "#;".repeat(200));
+    let cases = cases
+        .iter()
+        .copied()
+        .chain(vec![&*deep, &*quoted, &*commented]);
+    for case in cases {
+        let expected = case
+            .parse::<Value>()
+            .and_then(from_value::<Vec<u32>>)
+            .unwrap_err()
+            .to_string();
+        let from_str = from_str::<Vec<u32>>(case).unwrap_err();
+        let from_reader = from_reader::<_, Vec<u32>>(case.as_bytes()).unwrap_err();
+        assert_eq!(expected, from_str.to_string());
+        assert_eq!(expected, from_reader.to_string());
+    }
+}
+
+#[test]
+fn string_literals() {
+    let s = r#"("Hello, world!" "tab\there" "\u{1f600}\"\\" "")"#;
+    let expected = ["Hello, world!", "tab\there", "\u{1f600}\"\\", ""];
+    let value = expected.iter().map(|s| Value::Str(s.to_string())).collect();
+    assert_eq!(s.parse::<Value>().unwrap(), Value::List(value));
+    assert_eq!(from_str::<Vec<String>>(s).unwrap(), expected);
+    assert_eq!(
+        from_reader::<_, Vec<String>>(s.as_bytes()).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn quoted_atoms_are_strings() {
+    let s = "(42 |42| \"42\")";
+    let value = s.parse::<Value>().unwrap();
+    assert_eq!(value, crate::sexpr!((42 "42" "42")));
+    assert_eq!(value.to_string(), "(42 |42| |42|)");
+    assert_eq!(from_str::<(u8, String, String)>(s).unwrap().0, 42);
+    assert!(from_str::<(u8, u8, String)>(s).is_err());
+    assert!(from_value::<(u8, String, u8)>(value.clone()).is_err());
+    assert_eq!(
+        from_value::<[String; 3]>(value).unwrap(),
+        ["42", "42", "42"]
+    );
+}
+
+#[test]
+fn typed_atoms() {
+    let s = "(42 -7 +3 1.5 -2e3 .5 +inf.0 +nan.0 true false :key : 1e 0x1f inf)";
+    let value = s.parse::<Value>().unwrap();
+    let expected = Value::List(vec![
+        Value::Int(42),
+        Value::Int(-7),
+        Value::Int(3),
+        Value::Float(1.5),
+        Value::Float(-2000.0),
+        Value::Float(0.5),
+        Value::Float(f64::INFINITY),
+        Value::Float(f64::NAN),
+        Value::Bool(true),
+        Value::Bool(false),
+        Value::Keyword("key".to_string()),
+        Value::Sym(":".to_string()),
+        Value::Sym("1e".to_string()),
+        Value::Sym("0x1f".to_string()),
+        Value::Sym("inf".to_string()),
+    ]);
+    assert_eq!(value, expected);
+    assert_eq!(
+        value.to_string(),
+        "(42 -7 3 1.5 -2000.0 0.5 +inf.0 +nan.0 true false :key : 1e 0x1f inf)"
+    );
+    assert_ne!(Value::Float(0.0), Value::Float(-0.0));
+
+    // Symbols and keywords that would be read as something else are printed quoted.
+    let value = Value::List(
+        ["42", "true", "1.5", ":k", "+inf.0"]
+            .iter()
+            .map(|s| Value::Sym(s.to_string()))
+            .chain(vec![
+                Value::Keyword(String::new()),
+                Value::Keyword("a b".to_string()),
+                Value::Keyword(":".to_string()),
+            ])
+            .collect(),
+    );
+    assert_eq!(
+        value.to_string(),
+        "(|42| |true| |1.5| |:k| |+inf.0| |:| |:a\\ b| ::)"
+    );
+    assert_eq!(Atom::new("42").text(), "|42|");
+    assert_eq!(Node::new(&value).unwrap().to_string(), value.to_string());
+
+    let v = (
+        1u8,
+        -1i128,
+        1.0f64,
+        0.1f32,
+        false,
+        "1".to_string(),
+        'c',
+        f64::NEG_INFINITY,
+    );
+    let value = to_value(&v).unwrap();
+    assert_eq!(
+        value,
+        Value::List(vec![
+            Value::Int(1),
+            Value::Int(-1),
+            Value::Float(1.0),
+            Value::Float(0.1),
+            Value::Bool(false),
+            Value::Str("1".to_string()),
+            Value::Str("c".to_string()),
+            Value::Float(f64::NEG_INFINITY),
+        ])
+    );
+    let s = to_string(&v).unwrap();
+    assert_eq!(s, "(1 -1 1.0 0.1 false |1| |c| -inf.0)");
+    assert_eq!(s.parse::<Value>().unwrap(), value);
+    assert_eq!(
+        from_str::<(u8, i128, f64, f32, bool, String, char, f64)>(&s).unwrap(),
+        v
+    );
+    assert_eq!(
+        from_value::<(u8, i128, f64, f32, bool, String, char, f64)>(value).unwrap(),
+        v
+    );
+}
+
+/// A value deserialized with `deserialize_any`, recording which visitor method was called.
+#[derive(Debug, PartialEq)]
+enum Any {
+    Unit,
+    Bool(bool),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Str(String),
+    Seq(Vec<Any>),
+    Map(Vec<(Any, Any)>),
+}
+
+impl<'de> Deserialize<'de> for Any {
+    fn deserialize<D: serde::Deserializer<'de>>(de: D) -> Result<Any, D::Error> {
+        de.deserialize_any(AnyVisitor)
+    }
+}
+
+struct AnyVisitor;
+
+impl<'de> Visitor<'de> for AnyVisitor {
+    type Value = Any;
+
+    fn expecting(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.write_str("anything")
+    }
+
+    fn visit_unit<E>(self) -> Result<Any, E> {
+        Ok(Any::Unit)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Any, E> {
+        Ok(Any::Bool(b))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Any, E> {
+        Ok(Any::U64(n))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Any, E> {
+        Ok(Any::I64(n))
+    }
+
+    fn visit_f64<E>(self, f: f64) -> Result<Any, E> {
+        Ok(Any::F64(f))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Any, E> {
+        Ok(Any::Str(s.to_string()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Any, A::Error> {
+        let mut vs = Vec::new();
+        while let Some(v) = seq.next_element()? {
+            vs.push(v);
+        }
+        Ok(Any::Seq(vs))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Any, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Any::Map(entries))
+    }
+}
+
+#[test]
+fn deserialize_any_infers_types() {
+    let s = "(() 42 -7 1.5 true |true| \"42\" foo (1 []))";
+    let expected = Any::Seq(vec![
+        Any::Unit,
+        Any::U64(42),
+        Any::I64(-7),
+        Any::F64(1.5),
+        Any::Bool(true),
+        Any::Str("true".to_string()),
+        Any::Str("42".to_string()),
+        Any::Str("foo".to_string()),
+        Any::Seq(vec![Any::U64(1), Any::Unit]),
+    ]);
+    assert_eq!(from_str::<Any>(s).unwrap(), expected);
+    let value = s.parse::<Value>().unwrap();
+    assert_eq!(from_value::<Any>(value.clone()).unwrap(), expected);
+
+    let str = |s: &str| Any::Str(s.to_string());
+    let expected = Any::Seq(vec![
+        Any::Seq(vec![]),
+        str("42"),
+        str("-7"),
+        str("1.5"),
+        str("true"),
+        str("true"),
+        str("42"),
+        str("foo"),
+        Any::Seq(vec![str("1"), Any::Seq(vec![])]),
+    ]);
+    let mut de = Deserializer::from_str(s);
+    de.set_infer_types(false);
+    assert_eq!(Any::deserialize(&mut de).unwrap(), expected);
+    let mut de = ValueDeserializer::new(value);
+    de.set_infer_types(false);
+    assert_eq!(Any::deserialize(de).unwrap(), expected);
+}
+
+#[test]
+fn deserialize_any_infers_maps() {
+    let str = |s: &str| Any::Str(s.to_string());
+    let s = "(((a 1) [|b| c] (2 . d)) ((a 1) (b)) (((a) 1)))";
+    let map = Any::Map(vec![
+        (str("a"), Any::U64(1)),
+        (str("b"), str("c")),
+        (Any::U64(2), str("d")),
+    ]);
+    let not_maps = vec![
+        Any::Seq(vec![
+            Any::Seq(vec![str("a"), Any::U64(1)]),
+            Any::Seq(vec![str("b")]),
+        ]),
+        Any::Seq(vec![Any::Seq(vec![Any::Seq(vec![str("a")]), Any::U64(1)])]),
+    ];
+    let expected = Any::Seq(vec![map].into_iter().chain(not_maps).collect());
+    let mut de = Deserializer::from_str(s);
+    de.set_infer_maps(true);
+    assert_eq!(Any::deserialize(&mut de).unwrap(), expected);
+    let mut de = ValueDeserializer::new(s.parse::<Value>().unwrap());
+    de.set_infer_maps(true);
+    assert_eq!(Any::deserialize(de).unwrap(), expected);
+
+    // Association lists are sequences unless maps are turned on.
+    let expected = Any::Seq(vec![Any::Seq(vec![str("a"), Any::U64(1)])]);
+    assert_eq!(from_str::<Any>("((a 1))").unwrap(), expected);
+    assert_eq!(from_value::<Any>(crate::sexpr!(((a 1)))).unwrap(), expected);
+}
+
+#[test]
+fn invalid_options() {
+    let cases = [
+        (OptionEncoding::Wrapped, "1"),
+        (OptionEncoding::Wrapped, "(1 2)"),
+        (OptionEncoding::Tagged, "()"),
+        (OptionEncoding::Tagged, "|none|"),
+        (OptionEncoding::Tagged, "(some)"),
+        (OptionEncoding::Tagged, "(some 1 2)"),
+        (OptionEncoding::Tagged, "(1)"),
+        (OptionEncoding::Tagged, "((some) 1)"),
+    ];
+    for &(encoding, s) in &cases {
+        let mut de = Deserializer::from_str(s);
+        de.set_option_encoding(encoding);
+        assert!(Option::<u32>::deserialize(&mut de).is_err(), "{}", s);
+
+        let mut de = ValueDeserializer::new(s.parse().unwrap());
+        de.set_option_encoding(encoding);
+        assert!(Option::<u32>::deserialize(de).is_err(), "{}", s);
+    }
+}
+
+#[test]
+fn dotted_lists() {
+    let sym = |s: &str| Value::Sym(s.to_string());
+    let s = "((a . 1) (b . (2 3)) (c . (4 . 5)) (d . |.|) (.e f))";
+    let expected = Value::List(vec![
+        Value::DottedList(vec![sym("a")], Box::new(Value::Int(1))),
+        Value::List(vec![sym("b"), Value::Int(2), Value::Int(3)]),
+        Value::DottedList(vec![sym("c"), Value::Int(4)], Box::new(Value::Int(5))),
+        Value::DottedList(vec![sym("d")], Box::new(Value::Str(".".to_string()))),
+        Value::List(vec![sym(".e"), sym("f")]),
+    ]);
+    let value = s.parse::<Value>().unwrap();
+    assert_eq!(value, expected);
+    assert_eq!(
+        value.to_string(),
+        "((a . 1) (b 2 3) (c 4 . 5) (d . |.|) (.e f))"
+    );
+
+    // A dotted list with no elements is printed as its tail, since `(. a)` does not parse.
+    let value = Value::List(vec![
+        Value::DottedList(vec![], Box::new(sym("a"))),
+        Value::DottedList(
+            vec![],
+            Box::new(Value::DottedList(vec![], Box::new(Value::Int(1)))),
+        ),
+    ]);
+    assert_eq!(value.to_string(), "(a 1)");
+    assert_eq!(Node::new(&value).unwrap().to_string(), "(a 1)");
+
+    let s = "((a . 1) (b 2) (c . 3))";
+    let expected: BTreeMap<_, _> = vec![
+        ("a".to_string(), 1),
+        ("b".to_string(), 2),
+        ("c".to_string(), 3),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(from_str::<BTreeMap<String, u32>>(s).unwrap(), expected);
+    assert_eq!(
+        from_reader::<_, BTreeMap<String, u32>>(s.as_bytes()).unwrap(),
+        expected
+    );
+    let value = s.parse::<Value>().unwrap();
+    assert_eq!(
+        from_value::<BTreeMap<String, u32>>(value).unwrap(),
+        expected
+    );
+
+    assert!(from_str::<Vec<u32>>("(1 . 2)").is_err());
+    assert!(from_str::<BTreeMap<String, u32>>("((a 1 . 2))").is_err());
+}
+
+#[test]
+fn quote_shorthands() {
+    let s = "('a `(b ,c ,@d) ' ;comment\n e ,|@f| (unquote @g) (a . 'b))";
+    let expected = "((quote a) (quasiquote (b (unquote c) (unquote-splicing d))) (quote e) \
+                    (unquote |@f|) (unquote @g) (a quote b))";
+    let value = s.parse::<Value>().unwrap();
+    assert_eq!(value, expected.parse::<Value>().unwrap());
+
+    let options = PrintOptions {
+        quote_shorthand: true,
+        ..PrintOptions::default()
+    };
+    assert_eq!(
+        value.display(options).to_string(),
+        "('a `(b ,c ,@d) 'e ,|@f| (unquote @g) (a quote b))"
+    );
+
+    let expected = vec![
+        ("quote".to_string(), "a".to_string()),
+        ("unquote-splicing".to_string(), "b".to_string()),
+    ];
+    let s = "('a ,@b)";
+    assert_eq!(from_str::<Vec<(String, String)>>(s).unwrap(), expected);
+    assert_eq!(
+        from_reader::<_, Vec<(String, String)>>(s.as_bytes()).unwrap(),
+        expected
+    );
+    assert_eq!(
+        from_str::<Vec<Vec<String>>>("('#;x y)").unwrap(),
+        [["quote", "y"]]
+    );
+}
+
+#[test]
+fn brackets() {
+    let s = "(let [(x 1) {y . 2}] [x y])";
+    let value = s.parse::<Value>().unwrap();
+    assert_eq!(value, "(let ((x 1) (y . 2)) (x y))".parse().unwrap());
+
+    let value = Value::parse_with(
+        s,
+        ParseOptions {
+            keep_brackets: true,
+            ..ParseOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(value.to_string(), "(let [(x 1) (y . 2)] [x y])");
+
+    type Let = (String, BTreeMap<String, u32>, [String; 2]);
+    let (_, bindings, body) = from_str::<Let>(s).unwrap();
+    assert_eq!(bindings.len(), 2);
+    assert_eq!(body, ["x", "y"]);
+    assert_eq!(
+        from_value::<Let>(value).unwrap(),
+        from_str::<Let>(s).unwrap()
+    );
+}
+
+#[test]
+fn from_str_borrows_unescaped_symbols() {
+    let input = "(foo |bar|)";
+    let (foo, bar): (&str, String) = from_str(input).unwrap();
+    assert_eq!(foo.as_ptr(), input[1..].as_ptr());
+    assert_eq!(bar, "bar");
+}
+
+#[test]
+fn comments() {
+    let s = "; leading comment
+        (foo #| block #| nested |# comment |#
+         bar ; line comment
+         #; (skipped datum) baz
+         #;qux
+         #|x|#quux
+        )
+        ; trailing comment";
+    let expected = "(foo bar baz quux)".parse::<Value>().unwrap();
+    assert_eq!(s.parse::<Value>().unwrap(), expected);
+    assert_eq!(
+        from_str::<Vec<String>>(s).unwrap(),
+        ["foo", "bar", "baz", "quux"]
+    );
+    assert_eq!(
+        from_reader::<_, Vec<String>>(s.as_bytes()).unwrap(),
+        ["foo", "bar", "baz", "quux"]
+    );
+}
+
+#[test]
+fn whitespace_placement() {
+    let expected = "(a (b . c) 'd)".parse::<Value>().unwrap();
+    for s in &[
+        "( a ( b . c ) ' d )",
+        "\n(\ta(b\n.\tc)'d\n)\n",
+        "(\u{a0}a\u{2003}(b\u{3000}.\u{85}c)\u{2028}'d\u{a0})",
+        "(#|x|#a #|x|#(b;x\n.;x\nc);x\n'#;x d)",
+    ] {
+        assert_eq!(s.parse::<Value>().unwrap(), expected, "{:?}", s);
+    }
+    assert_eq!(from_str::<Vec<String>>("( a\u{a0}b )").unwrap(), ["a", "b"]);
+}
+
+#[test]
+fn event_reader() {
+    let read_values = |s: &str| -> Result<Vec<Value>, Error> {
+        let mut reader = EventReader::from_str(s);
+        let mut lists = vec![(Vec::new(), false)];
+        while let Some((_, event)) = reader.next_event()? {
+            let value = match event {
+                Event::StartList => {
+                    lists.push((Vec::new(), false));
+                    continue;
+                }
+                Event::Dot => {
+                    lists.last_mut().unwrap().1 = true;
+                    continue;
+                }
+                Event::EndList => match lists.pop().unwrap() {
+                    (mut vs, true) => {
+                        let tail = vs.pop().unwrap();
+                        Value::dotted_list(vs, tail)
+                    }
+                    (vs, false) => Value::List(vs),
                 },
-            )
-            .boxed()
+                Event::Atom(s) => Value::Sym(s.to_string()),
+                Event::Str(s) => Value::Str(s.to_string()),
+            };
+            lists.last_mut().unwrap().0.push(value);
+        }
+        Ok(lists.pop().unwrap().0)
+    };
+
+    for s in &[
+        "(a (b . c) 'd [e] \"f g\")",
+        "x",
+        "(a . (b #;c d))",
+        "`(,a ,@b)",
+    ] {
+        assert_eq!(read_values(s).unwrap(), [s.parse::<Value>().unwrap()]);
+    }
+    assert_eq!(read_values("a (b) c").unwrap().len(), 3);
+    for s in &[
+        "(1 2",
+        "(1 (2 . ))",
+        "(1 (2 . 3 4))",
+        "(1 (2 . . 3))",
+        "(1 ')",
+        "(1 2]",
+        "(. a)",
+        "(a |b c|)",
+    ] {
+        let err = read_values(s).unwrap_err();
+        assert_eq!(err.to_string(), s.parse::<Value>().unwrap_err().to_string());
+    }
+
+    let s = "(a (b c) . d) ; comment\n  e";
+    let mut reader = EventReader::from_reader(BufReader::with_capacity(1, s.as_bytes()));
+    assert_eq!(reader.next_event().unwrap().unwrap().1, Event::StartList);
+    assert_eq!(reader.next_event().unwrap().unwrap().1, Event::Atom("a"));
+    assert!(reader.skip_value().unwrap());
+    assert!(reader.skip_value().unwrap());
+    assert!(!reader.skip_value().unwrap());
+    assert_eq!(reader.next_event().unwrap().unwrap().1, Event::EndList);
+    let (pos, event) = reader.next_event().unwrap().unwrap();
+    assert_eq!(event, Event::Atom("e"));
+    assert_eq!((pos.offset, pos.line, pos.column), (26, 2, 3));
+    assert!(!reader.skip_value().unwrap());
+    assert!(reader.next_event().unwrap().is_none());
+}
+
+#[test]
+fn lexer_tokens() {
+    let s = "#| a |# [x . 'y] ,@\"z\\n\" #;.5\n";
+    let tokens = Lexer::new(s).map(Result::unwrap).collect::<Vec<_>>();
+    let kinds = tokens.iter().map(|token| token.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::BlockComment,
+            TokenKind::Whitespace,
+            TokenKind::Open,
+            TokenKind::Symbol,
+            TokenKind::Whitespace,
+            TokenKind::Dot,
+            TokenKind::Whitespace,
+            TokenKind::Quote,
+            TokenKind::Symbol,
+            TokenKind::Close,
+            TokenKind::Whitespace,
+            TokenKind::Quote,
+            TokenKind::String,
+            TokenKind::Whitespace,
+            TokenKind::DatumComment,
+            TokenKind::Symbol,
+            TokenKind::Whitespace,
+        ]
+    );
+    for token in &tokens {
+        assert_eq!(&s[token.span.clone()], token.text);
+    }
+    assert_eq!(tokens[11].text, ",@");
+    assert_eq!(tokens[15].text, ".5");
+
+    for s in &["(a \\", "(a |b c|)", "\"a\\q\"", "#| a"] {
+        let lexer_err = Lexer::new(s).find_map(Result::err).unwrap();
+        let parser_err = s.parse::<Value>().unwrap_err();
+        assert_eq!(lexer_err.to_string(), parser_err.to_string());
+    }
+}
+
+#[test]
+fn document_round_trip() {
+    let inputs = [
+        "a",
+        "#| head |# (a #;(b c) [d . e]\t'f ,@g `(h . ,i)) ; tail",
+        "{ \"s t\" |u\\ v|\u{a0}#| x |#\n  (w x . y) } \n",
+        "(#;a #;b)",
+    ];
+    for s in &inputs {
+        let doc = s.parse::<Document>().unwrap();
+        assert_eq!(&doc.to_string(), s);
+        if let Ok(value) = s.parse::<Value>() {
+            assert_eq!(doc.root().to_value(), value);
+        }
+    }
+    for s in &["", "  ; comment\n", "(a", "(a . b c)", "a b", "#; a"] {
+        let doc_err = s.parse::<Document>().unwrap_err();
+        let value_err = s.parse::<Value>().unwrap_err();
+        assert_eq!(doc_err.to_string(), value_err.to_string());
+    }
+
+    let mut doc = "(list\n  a ; first\n  b\n  . c)"
+        .parse::<Document>()
+        .unwrap();
+    let list = doc.root_mut().as_list_mut().unwrap();
+    let _ = list.remove(1);
+    list.insert(1, Node::atom("a b"));
+    list.push(Node::new(&Value::List(vec![])).unwrap());
+    list.insert(0, Node::atom("z"));
+    assert_eq!(
+        doc.to_string(),
+        "(z list\n  |a\\ b| ; first\n  b\n  ()\n  . c)"
+    );
+}
+
+#[test]
+fn parse_recovering() {
+    let options = ParseOptions {
+        keep_brackets: false,
+        limits: Limits {
+            max_depth: 2,
+            ..Limits::default()
+        },
+    };
+    let cases = [
+        (
+            "[a (b] c",
+            "(a (b))",
+            vec![(5, "mismatched closing bracket"), (7, "trailing input")],
+        ),
+        (
+            "(a ((b)) 'c",
+            "(a () (quote c))",
+            vec![(4, "depth limit"), (11, "unclosed list")],
+        ),
+        (
+            "(a . ) \\ ')",
+            "(a)",
+            vec![
+                (5, "unexpected closing"),
+                (7, "trailing input"),
+                (7, "unexpected '\\\\'"),
+                (10, "unexpected closing"),
+            ],
+        ),
+        (
+            "#| a",
+            "()",
+            vec![(0, "unterminated block comment"), (4, "empty input")],
+        ),
+    ];
+    for (s, expected, errors) in &cases {
+        let (doc, errs) = Document::parse_recovering(s, options);
+        assert_eq!(&doc.to_string(), s);
+        assert_eq!(doc.root().to_value(), expected.parse::<Value>().unwrap());
+        assert_eq!(errs.len(), errors.len(), "{:?}", errs);
+        for (err, (offset, message)) in errs.iter().zip(errors) {
+            assert_eq!(err.span().unwrap().start.offset, *offset);
+            assert!(err.to_string().contains(message), "{}", err);
+        }
+    }
+
+    let (doc, _) = Document::parse_recovering("[a (b] c", options);
+    let list = doc.root().as_list().unwrap();
+    assert!(list.is_closed());
+    assert!(!list.get(1).unwrap().as_list().unwrap().is_closed());
+}
+
+#[test]
+fn escapes() {
+    let s = "|a\\nb\\u{202e}\\ c\\q\\u|";
+    let expected = "a\nb\u{202e} cqu";
+    assert_eq!(
+        s.parse::<Value>().unwrap(),
+        Value::Str(expected.to_string())
+    );
+    assert_eq!(from_str::<String>(s).unwrap(), expected);
+    assert_eq!(
+        from_reader::<_, String>(BufReader::with_capacity(1, s.as_bytes())).unwrap(),
+        expected
+    );
+
+    for s in &["|\\u{d800}|", "|a\\u{|", "|\\u{1234567}|", "\"a\\u\""] {
+        let value_err = s.parse::<Value>().unwrap_err();
+        let de_err = from_str::<String>(s).unwrap_err();
+        assert_eq!(value_err.to_string(), de_err.to_string());
+        assert!(matches!(
+            value_err,
+            Error::Syntax(SyntaxError::InvalidEscape, _)
+        ));
+    }
+
+    let value = Value::Sym("\tx\u{a0}y\u{200b}\r\n".to_string());
+    assert_eq!(value.to_string(), "|\\tx\\u{a0}y\\u{200b}\\r\\n|");
+    let options = PrintOptions {
+        string_style: StringStyle::Quoted,
+        ..PrintOptions::default()
+    };
+    assert_eq!(
+        value.display(options).to_string(),
+        "\"\\tx\\u{a0}y\\u{200b}\\r\\n\""
+    );
+    let options = PrintOptions {
+        escape_invisible: false,
+        ..PrintOptions::default()
+    };
+    assert_eq!(
+        value.display(options).to_string(),
+        "|\\\tx\\\u{a0}y\u{200b}\\\r\\\n|"
+    );
+    assert_eq!(
+        Value::Sym("\u{202e}".to_string())
+            .display(options)
+            .to_string(),
+        "\u{202e}"
+    );
+}
+
+#[test]
+fn depth_limit() {
+    let is_depth_limit = |r: Result<(), Error>| match r {
+        Err(Error::LimitExceeded(Limit::Depth(DEFAULT_MAX_DEPTH), _)) => true,
+        r => panic!("expected the depth limit to be exceeded, got {:?}", r),
+    };
+
+    let s = "(".repeat(100_000);
+    let span = match s.parse::<Value>() {
+        Err(Error::LimitExceeded(Limit::Depth(DEFAULT_MAX_DEPTH), Some(span))) => span,
+        r => panic!("expected the depth limit to be exceeded, got {:?}", r),
+    };
+    assert_eq!((span.start.offset, span.end.offset), (128, 129));
+    assert!(is_depth_limit(from_str::<IgnoredAny>(&s).map(drop)));
+    assert!(is_depth_limit(
+        from_reader::<_, IgnoredAny>(s.as_bytes()).map(drop)
+    ));
+    assert!(is_depth_limit(from_str::<Vec<u32>>(&s).map(drop)));
+    let mut stream = Deserializer::from_str(&s).into_iter::<IgnoredAny>();
+    assert!(is_depth_limit(stream.next().unwrap().map(drop)));
+    assert!(stream.next().is_none());
+
+    let s = format!("{}x{}", "(".repeat(128), ")".repeat(128));
+    assert!(s.parse::<Value>().is_ok());
+    assert!(from_str::<IgnoredAny>(&s).is_ok());
+
+    let mut value = Value::Sym("x".to_string());
+    for _ in 0..200 {
+        value = Value::List(vec![value]);
+    }
+    assert!(is_depth_limit(from_value::<IgnoredAny>(value).map(drop)));
+
+    // Deserializing a value directly, without `from_value` checking it first, stops at the limit
+    // too.
+    let value = deep_value(&[0, 1], DEEP, "x");
+    assert!(is_depth_limit(
+        Any::deserialize(ValueDeserializer::new(value.clone())).map(drop)
+    ));
+    assert!(is_depth_limit(Any::deserialize(value).map(drop)));
+    let value = deep_value(&[0, 1], DEFAULT_MAX_DEPTH, "x");
+    assert!(Any::deserialize(value.clone()).is_ok());
+    assert!(is_depth_limit(
+        Any::deserialize(Value::List(vec![value])).map(drop)
+    ));
+
+    #[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+    struct Nested(usize);
+    impl Serialize for Nested {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self.0 {
+                0 => serializer.serialize_unit(),
+                n => [Nested(n - 1)].serialize(serializer),
+            }
+        }
+    }
+    let value = to_value(&Nested(DEFAULT_MAX_DEPTH - 1)).unwrap();
+    assert!(from_value::<IgnoredAny>(value).is_ok());
+    assert!(is_depth_limit(
+        to_value(&Nested(DEFAULT_MAX_DEPTH)).map(drop)
+    ));
+    assert!(is_depth_limit(to_value(&Nested(100_000)).map(drop)));
+    assert!(is_depth_limit(to_string(&Nested(100_000)).map(drop)));
+
+    // Each field is a `(name value)` list inside the struct's list, so a struct nested `n` deep is
+    // `2 * n` lists deep.
+    #[derive(Debug)]
+    struct NestedStruct(usize);
+    impl Serialize for NestedStruct {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeStruct;
+            match self.0 {
+                0 => serializer.serialize_u8(0),
+                n => {
+                    let mut s = serializer.serialize_struct("NestedStruct", 1)?;
+                    s.serialize_field("c", &NestedStruct(n - 1))?;
+                    s.end()
+                }
+            }
+        }
+    }
+    let s = to_string(&NestedStruct(DEFAULT_MAX_DEPTH / 2)).unwrap();
+    assert!(s.parse::<Value>().is_ok());
+    assert!(from_str::<IgnoredAny>(&s).is_ok());
+    assert!(is_depth_limit(
+        to_string(&NestedStruct(DEFAULT_MAX_DEPTH / 2 + 1)).map(drop)
+    ));
+    let mut de = ValueDeserializer::new(s.parse().unwrap());
+    de.set_infer_maps(true);
+    assert!(Any::deserialize(de).is_ok());
+    let serializer = Serializer::with_max_depth(DEFAULT_MAX_DEPTH + 2);
+    let value = NestedStruct(DEFAULT_MAX_DEPTH / 2 + 1)
+        .serialize(serializer)
+        .unwrap();
+    let mut de = ValueDeserializer::new(value);
+    de.set_infer_maps(true);
+    assert!(is_depth_limit(Any::deserialize(de).map(drop)));
+    let map = |n| {
+        vec![(Nested(n), ())]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>()
+    };
+    let s = to_string(&map(DEFAULT_MAX_DEPTH - 3)).unwrap();
+    assert!(s.parse::<Value>().is_ok());
+    assert!(is_depth_limit(
+        to_string(&map(DEFAULT_MAX_DEPTH - 2)).map(drop)
+    ));
+}
+
+#[test]
+fn size_limits() {
+    let limits = Limits {
+        max_input_len: 12,
+        max_symbol_len: 5,
+        max_list_len: 3,
+        ..Limits::default()
+    };
+    let options = ParseOptions {
+        limits,
+        ..ParseOptions::default()
+    };
+    let cases = [
+        ("(a b c d)", Limit::ListLength(3), 7),
+        ("(a b c . d)", Limit::ListLength(3), 9),
+        ("((b c d e))", Limit::ListLength(3), 8),
+        ("'(a b c d)", Limit::ListLength(3), 8),
+        ("#;(a b c d)", Limit::ListLength(3), 9),
+        ("(abcdef)", Limit::SymbolLength(5), 1),
+        ("(|abc\\def|)", Limit::SymbolLength(5), 1),
+        ("(\"ab\\tcde\")", Limit::SymbolLength(5), 1),
+        ("(abcd efg hi)", Limit::InputLength(12), 12),
+        ("(abcd efgh \u{e9})", Limit::InputLength(12), 11),
+    ];
+    for &(case, limit, offset) in cases.iter() {
+        let expected = Value::parse_with(case, options).unwrap_err();
+        match expected {
+            Error::LimitExceeded(l, Some(span)) if l == limit => {
+                assert_eq!(span.start.offset, offset, "{:?}", case);
+            }
+            ref err => panic!("{:?} failed with {:?}", case, err),
+        }
+        let expected = expected.to_string();
+        let from_str = from_str_with::<IgnoredAny>(case, limits).unwrap_err();
+        let reader = BufReader::with_capacity(1, case.as_bytes());
+        let from_reader = from_reader_with::<_, IgnoredAny>(reader, limits).unwrap_err();
+        assert_eq!(expected, from_str.to_string());
+        assert_eq!(expected, from_reader.to_string());
+
+        // Datum comments are not part of the parsed value.
+        if limit != Limit::InputLength(12) && !case.starts_with('#') {
+            let value = case.parse::<Value>().unwrap();
+            match from_value_with::<IgnoredAny>(value, limits) {
+                Err(Error::LimitExceeded(l, None)) if l == limit => {}
+                r => panic!("{:?} deserialized to {:?}", case, r),
+            }
+        }
+    }
+
+    for case in ["(a b c)", "(a b . c)", "(abcde |f|)", "'(a b c)"].iter() {
+        let value = Value::parse_with(case, options).unwrap();
+        assert!(from_str_with::<IgnoredAny>(case, limits).is_ok());
+        assert!(from_reader_with::<_, IgnoredAny>(case.as_bytes(), limits).is_ok());
+        assert!(from_value_with::<IgnoredAny>(value, limits).is_ok());
     }
 }
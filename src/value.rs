@@ -1,49 +1,340 @@
-use crate::needs_quoting;
-use std::fmt::{Display, Formatter, Result as FmtResult};
+use crate::{needs_quoting, Error, Limit, Limits, Result};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+    iter::Chain,
+    mem::{replace, take},
+    option,
+    slice::Iter,
+};
 
 /// An s-expression.
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+///
+/// Atoms keep whether they were written bare or quoted: `foo` parses to a `Sym`, while `|foo|` and
+/// `"foo"` both parse to a `Str`. A `Str` is always printed quoted, so the distinction survives
+/// printing and parsing again, and a `Str` cannot be deserialized as a number or boolean.
+///
+/// Bare atoms that are written as integers, floats, `true` or `false`, or keywords such as
+/// `:foo`, are parsed to an `Int`, `Float`, `Bool` or `Keyword`. `to_value` produces these for the
+/// corresponding Rust types, and a `Str` for strings and characters.
+///
+/// Dropping, cloning, comparing, hashing and printing a value does not recurse, so values that
+/// are nested arbitrarily deeply can be handled without overflowing the stack.
+///
+/// Since `Value` implements `Drop` to do this, the contents of a value cannot be moved out of it
+/// with a pattern such as `match value { Value::List(vs) => vs, _ => ... }`. Match on a mutable
+/// reference and use `std::mem::take` instead:
+///
+/// ```
+/// # use serde_sexpr::{sexpr, Value};
+/// let mut value = sexpr!((a b));
+/// let vs = match &mut value {
+///     Value::List(vs) => std::mem::take(vs),
+///     _ => Vec::new(),
+/// };
+/// assert_eq!(vs.len(), 2);
+/// ```
+#[derive(Debug)]
 pub enum Value {
     /// A list.
     List(Vec<Value>),
 
-    /// A symbol.
+    /// A list written with square or curly brackets, such as `[a b]` or `{a b}`.
+    ///
+    /// This is only produced when parsing with `ParseOptions::keep_brackets` set; otherwise, such
+    /// lists are parsed as a `List`. It is deserialized in the same way as a `List`.
+    Bracketed(Bracket, Vec<Value>),
+
+    /// A dotted list, such as `(a . b)` or `(a b . c)`, whose final tail is not the empty list.
+    ///
+    /// The parser never produces a dotted list with no elements, or with a list or dotted list as
+    /// its tail; `(a . (b c))` is read as `(a b c)`, and `(a . (b . c))` as `(a b . c)`. Since
+    /// `(. a)` does not parse, a dotted list with no elements is printed as just its tail.
+    DottedList(Vec<Value>, Box<Value>),
+
+    /// A symbol, written bare.
+    ///
+    /// A symbol that cannot be written bare, such as one containing whitespace, or that would be
+    /// read as another kind of atom, such as `42` or `true`, is printed quoted, so it parses back
+    /// as a `Str`.
     Sym(String),
+
+    /// A string, written as an escaped symbol or a string literal.
+    Str(String),
+
+    /// A keyword, written as a bare atom starting with `:`, such as `:foo`. The `:` is not part of
+    /// the name.
+    ///
+    /// A keyword whose name is empty or cannot be written bare is printed quoted, so it parses
+    /// back as a `Str`.
+    Keyword(String),
+
+    /// An integer, written in decimal, such as `42` or `-7`. Integers that do not fit in an `i128`
+    /// are parsed as a `Sym`.
+    Int(i128),
+
+    /// A floating-point number, written with a decimal point or exponent, such as `1.5` or `1e10`.
+    /// Infinities and NaN are written `+inf.0`, `-inf.0` and `+nan.0`.
+    ///
+    /// Floats are compared by their IEEE 754 total order, except that all NaNs are equal to each
+    /// other, so `-0.0` and `0.0` are different values.
+    Float(f64),
+
+    /// A boolean, written `true` or `false`.
+    Bool(bool),
 }
 
-impl Display for Value {
-    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+impl Value {
+    /// Returns a wrapper around the value whose `Display` implementation prints it with the given
+    /// options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_sexpr::{sexpr, PrintOptions, StringStyle};
+    /// let value = sexpr!(("Hello, world!" "\"Hi\""));
+    /// assert_eq!(value.to_string(), "(|Hello,\\ world!| |\\\"Hi\\\"|)");
+    ///
+    /// let options = PrintOptions {
+    ///     string_style: StringStyle::Quoted,
+    ///     ..PrintOptions::default()
+    /// };
+    /// assert_eq!(value.display(options).to_string(), "(\"Hello, world!\" \"\\\"Hi\\\"\")");
+    /// ```
+    pub fn display(&self, options: PrintOptions) -> DisplayValue<'_> {
+        DisplayValue {
+            value: self,
+            options,
+        }
+    }
+
+    /// Returns the value of a bare atom, which is a `Sym` unless the atom is written as an integer,
+    /// float, boolean or keyword.
+    pub(crate) fn bare_atom(s: String) -> Value {
+        Value::typed_atom(&s).unwrap_or(Value::Sym(s))
+    }
+
+    /// Returns the value of a bare atom if it is written as an integer, float, boolean or
+    /// keyword, or `None` if it is a symbol.
+    pub(crate) fn typed_atom(s: &str) -> Option<Value> {
+        if let Some(name) = s.strip_prefix(':').filter(|name| !name.is_empty()) {
+            return Some(Value::Keyword(name.to_string()));
+        }
+        match s {
+            "true" => return Some(Value::Bool(true)),
+            "false" => return Some(Value::Bool(false)),
+            _ => {}
+        }
+        let digits = s.strip_prefix(['+', '-']).unwrap_or(s);
+        if !digits.starts_with(|ch: char| ch.is_ascii_digit() || ch == '.') {
+            special_float(s).map(Value::Float)
+        } else if digits.bytes().all(|b| b.is_ascii_digit()) {
+            s.parse().ok().map(Value::Int)
+        } else if digits
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+        {
+            s.parse().ok().map(Value::Float)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the text of an atom, as it would be written bare, or `None` for a list.
+    pub(crate) fn atom_text(&self) -> Option<Cow<'_, str>> {
         match self {
-            Value::List(l) => {
-                write!(fmt, "(")?;
-                let mut first = true;
-                for x in l {
-                    let sep = if first {
-                        first = false;
-                        ""
-                    } else {
-                        " "
-                    };
-                    write!(fmt, "{}{}", sep, x)?;
+            Value::Sym(s) | Value::Str(s) => Some(Cow::Borrowed(s)),
+            Value::Keyword(name) => Some(Cow::Owned(format!(":{}", name))),
+            Value::Int(n) => Some(Cow::Owned(n.to_string())),
+            Value::Float(f) => Some(Cow::Owned(float_text(*f))),
+            Value::Bool(b) => Some(Cow::Borrowed(if *b { "true" } else { "false" })),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the value is an atom, rather than a list.
+    pub(crate) fn is_atom(&self) -> bool {
+        !matches!(
+            self,
+            Value::List(_) | Value::Bracketed(..) | Value::DottedList(..)
+        )
+    }
+
+    /// Creates a dotted list with the given elements and tail, merging a list or dotted list tail
+    /// into the elements as the parser does.
+    pub(crate) fn dotted_list(mut vs: Vec<Value>, mut tail: Value) -> Value {
+        match &mut tail {
+            Value::List(tail) => {
+                vs.append(tail);
+                Value::List(vs)
+            }
+            Value::DottedList(tail, last) => {
+                vs.append(tail);
+                Value::DottedList(vs, Box::new(last.take()))
+            }
+            _ => Value::DottedList(vs, Box::new(tail)),
+        }
+    }
+
+    /// Moves the value out, leaving an empty list in its place. Since `Value` implements `Drop`,
+    /// this is needed to move the contents out of a value.
+    pub(crate) fn take(&mut self) -> Value {
+        replace(self, Value::List(Vec::new()))
+    }
+
+    /// Returns an iterator over the events of a pre-order traversal of the value.
+    fn events(&self) -> Events<'_> {
+        Events {
+            next: Some(self),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Returns an iterator over the elements of a list, followed by the tail of a dotted list.
+    fn children(&self) -> Children<'_> {
+        match self {
+            Value::List(vs) | Value::Bracketed(_, vs) => vs.iter().chain(None),
+            Value::DottedList(vs, tail) => vs.iter().chain(Some(&**tail)),
+            _ => [].iter().chain(None),
+        }
+    }
+
+    /// Returns a copy of the value if it is an atom, which can be cloned without recursing.
+    fn clone_atom(&self) -> Option<Value> {
+        match self {
+            Value::Sym(s) => Some(Value::Sym(s.clone())),
+            Value::Str(s) => Some(Value::Str(s.clone())),
+            Value::Keyword(name) => Some(Value::Keyword(name.clone())),
+            Value::Int(n) => Some(Value::Int(*n)),
+            Value::Float(f) => Some(Value::Float(*f)),
+            Value::Bool(b) => Some(Value::Bool(*b)),
+            _ => None,
+        }
+    }
+
+    /// Moves the elements of a list, and the tail of a dotted list, onto `stack`, except for
+    /// atoms, which are dropped.
+    fn take_children(&mut self, stack: &mut Vec<Value>) {
+        let (vs, tail) = match self {
+            Value::List(vs) | Value::Bracketed(_, vs) => (vs, None),
+            Value::DottedList(vs, tail) => (vs, Some(tail.take())),
+            _ => return,
+        };
+        stack.extend(vs.drain(..).chain(tail).filter(|value| !value.is_atom()));
+    }
+
+    /// Returns an error if the value has lists nested too deeply, or lists or symbols that are
+    /// too long, without recursing.
+    pub(crate) fn check_limits(&self, limits: Limits) -> Result<()> {
+        let exceeded = |limit| Err(Error::LimitExceeded(limit, None));
+        let mut stack = vec![(self, 0)];
+        while let Some((value, depth)) = stack.pop() {
+            let children = match value {
+                Value::Sym(s) | Value::Str(s) | Value::Keyword(s)
+                    if s.len() > limits.max_symbol_len =>
+                {
+                    return exceeded(Limit::SymbolLength(limits.max_symbol_len));
                 }
-                write!(fmt, ")")
-            }
-            Value::Sym(s) => {
-                if s.chars().any(needs_quoting) || s.is_empty() {
-                    write!(fmt, "|")?;
-                    for ch in s.chars() {
-                        let prefix = if needs_quoting(ch) { "\\" } else { "" };
-                        write!(fmt, "{}{}", prefix, ch)?;
-                    }
-                    write!(fmt, "|")
-                } else {
-                    write!(fmt, "{}", s)
+                atom if atom.is_atom() => continue,
+                list => list.children(),
+            };
+            if depth >= limits.max_depth {
+                return exceeded(Limit::Depth(limits.max_depth));
+            } else if children.clone().count() > limits.max_list_len {
+                return exceeded(Limit::ListLength(limits.max_list_len));
+            }
+            stack.extend(children.rev().map(|v| (v, depth + 1)));
+        }
+        Ok(())
+    }
+}
+
+impl Clone for Value {
+    fn clone(&self) -> Value {
+        // The lists being cloned, with their remaining children and the clones of the others.
+        let mut stack: Vec<(&Value, Children<'_>, Vec<Value>)> = Vec::new();
+        let mut next = Some(self);
+        loop {
+            if let Some(value) = next.take() {
+                match (value.clone_atom(), stack.last_mut()) {
+                    (Some(atom), Some((_, _, vs))) => vs.push(atom),
+                    (Some(atom), None) => return atom,
+                    (None, _) => stack.push((value, value.children(), Vec::new())),
+                }
+            }
+
+            let (list, children, vs) = stack.last_mut().unwrap();
+            if let Some(child) = children.next() {
+                next = Some(child);
+                continue;
+            }
+            let mut vs = take(vs);
+            let cloned = match list {
+                Value::List(_) => Value::List(vs),
+                Value::Bracketed(bracket, _) => Value::Bracketed(*bracket, vs),
+                Value::DottedList(..) => {
+                    let tail = vs.pop().unwrap();
+                    Value::DottedList(vs, Box::new(tail))
                 }
+                _ => unreachable!(),
+            };
+            let _ = stack.pop();
+            match stack.last_mut() {
+                Some((_, _, vs)) => vs.push(cloned),
+                None => return cloned,
             }
         }
     }
 }
 
+impl Drop for Value {
+    fn drop(&mut self) {
+        // Move the descendants that are lists onto a stack, so they are dropped once they have
+        // no children.
+        let mut stack = Vec::new();
+        self.take_children(&mut stack);
+        while let Some(mut value) = stack.pop() {
+            value.take_children(&mut stack);
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.events().eq(other.events())
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        self.events().cmp(other.events())
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for event in self.events() {
+            event.hash(state);
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        self.display(PrintOptions::default()).fmt(fmt)
+    }
+}
+
 impl From<String> for Value {
     fn from(s: String) -> Value {
         Value::Sym(s)
@@ -55,3 +346,405 @@ impl From<Vec<Value>> for Value {
         Value::List(l)
     }
 }
+
+/// A kind of bracket, other than parentheses, that can surround a list.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Bracket {
+    /// Square brackets, as in `[a b]`.
+    Square,
+
+    /// Curly brackets, as in `{a b}`.
+    Curly,
+}
+
+impl Bracket {
+    /// Returns the kind of bracket that starts with the given opening bracket, if any.
+    pub(crate) fn from_open(open: char) -> Option<Bracket> {
+        match open {
+            '[' => Some(Bracket::Square),
+            '{' => Some(Bracket::Curly),
+            _ => None,
+        }
+    }
+
+    /// Returns the opening and closing bracket characters.
+    fn chars(self) -> (char, char) {
+        match self {
+            Bracket::Square => ('[', ']'),
+            Bracket::Curly => ('{', '}'),
+        }
+    }
+}
+
+/// Options controlling how a `Value` is printed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PrintOptions {
+    /// How strings, and symbols that cannot be printed bare, are quoted.
+    pub string_style: StringStyle,
+
+    /// Whether lists such as `(quote x)` and `(unquote-splicing x)` are printed with the
+    /// corresponding shorthand, such as `'x` and `,@x`.
+    pub quote_shorthand: bool,
+
+    /// Whether control characters, whitespace other than spaces, and characters that are usually
+    /// invisible, such as zero-width spaces and bidirectional overrides, are printed as escape
+    /// sequences such as `\n` and `\u{202e}`. This makes every value print as a single line, in
+    /// which every character can be seen. On by default.
+    pub escape_invisible: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> PrintOptions {
+        PrintOptions {
+            string_style: StringStyle::default(),
+            quote_shorthand: false,
+            escape_invisible: true,
+        }
+    }
+}
+
+/// How strings, and symbols that contain special characters (or are empty), are quoted when
+/// printed.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum StringStyle {
+    /// As an escaped symbol, such as `|Hello,\ world!|`.
+    #[default]
+    Escaped,
+
+    /// As a string literal, such as `"Hello, world!"`.
+    Quoted,
+}
+
+/// A wrapper around a `Value` that prints it with the given options. Returned by
+/// `Value::display`.
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayValue<'a> {
+    value: &'a Value,
+    options: PrintOptions,
+}
+
+impl<'a> Display for DisplayValue<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        // The lists being printed, with their remaining elements, tail and closing bracket, and
+        // whether any elements have been printed yet.
+        let mut stack: Vec<(Iter<'_, Value>, Option<&Value>, char, bool)> = Vec::new();
+        let mut next = Some(self.value);
+        loop {
+            match next.take() {
+                Some(Value::List(l)) => match quote_prefix(l) {
+                    Some(prefix) if self.options.quote_shorthand => {
+                        fmt.write_str(prefix)?;
+                        next = Some(&l[1]);
+                        continue;
+                    }
+                    _ => {
+                        fmt.write_str("(")?;
+                        stack.push((l.iter(), None, ')', false));
+                    }
+                },
+                Some(Value::Bracketed(bracket, l)) => {
+                    let (open, close) = bracket.chars();
+                    write!(fmt, "{}", open)?;
+                    stack.push((l.iter(), None, close, false));
+                }
+                Some(Value::DottedList(l, tail)) if l.is_empty() => {
+                    next = Some(tail);
+                    continue;
+                }
+                Some(Value::DottedList(l, tail)) => {
+                    fmt.write_str("(")?;
+                    stack.push((l.iter(), Some(&**tail), ')', false));
+                }
+                Some(Value::Sym(s)) => write_sym(fmt, s, self.options)?,
+                Some(Value::Str(s)) => write_str(fmt, s, self.options)?,
+                Some(Value::Keyword(name)) => write_keyword(fmt, name, self.options)?,
+                Some(Value::Int(n)) => write!(fmt, "{}", n)?,
+                Some(Value::Float(f)) => fmt.write_str(&float_text(*f))?,
+                Some(Value::Bool(b)) => write!(fmt, "{}", b)?,
+                None => {}
+            }
+
+            let (l, tail, close, started) = match stack.last_mut() {
+                Some(frame) => frame,
+                None => return Ok(()),
+            };
+            let sep = if *started { " " } else { "" };
+            if let Some(x) = l.next() {
+                fmt.write_str(sep)?;
+                *started = true;
+                next = Some(x);
+            } else if let Some(tail) = tail.take() {
+                write!(fmt, "{}. ", sep)?;
+                next = Some(tail);
+            } else {
+                write!(fmt, "{}", close)?;
+                let _ = stack.pop();
+            }
+        }
+    }
+}
+
+/// Prints a symbol, quoting it if it cannot be written bare or would be read as another kind of
+/// atom.
+fn write_sym(fmt: &mut Formatter, s: &str, options: PrintOptions) -> FmtResult {
+    if can_be_bare(s, options) && Value::typed_atom(s).is_none() {
+        fmt.write_str(s)
+    } else {
+        write_str(fmt, s, options)
+    }
+}
+
+/// Prints a keyword, quoting it if its name is empty or cannot be written bare.
+fn write_keyword(fmt: &mut Formatter, name: &str, options: PrintOptions) -> FmtResult {
+    let s = format!(":{}", name);
+    if !name.is_empty() && can_be_bare(&s, options) {
+        fmt.write_str(&s)
+    } else {
+        write_str(fmt, &s, options)
+    }
+}
+
+/// Returns whether the text of an atom can be written bare and be read back as a single atom with
+/// the same text.
+fn can_be_bare(s: &str, options: PrintOptions) -> bool {
+    !s.is_empty()
+        && !s
+            .chars()
+            .any(|ch| needs_quoting(ch) || (options.escape_invisible && needs_escaping(ch)))
+        && s != "."
+        && !s.starts_with(['\'', '`', ','])
+}
+
+/// Returns the text of a float, such that it is parsed back as the same float.
+fn float_text(f: f64) -> String {
+    if f.is_nan() {
+        "+nan.0".to_string()
+    } else if f.is_infinite() {
+        let sign = if f > 0.0 { '+' } else { '-' };
+        format!("{}inf.0", sign)
+    } else {
+        // Unlike `Display`, `Debug` always includes a decimal point or exponent.
+        format!("{:?}", f)
+    }
+}
+
+/// Returns the float written as `+inf.0`, `-inf.0` or `+nan.0`, if any.
+fn special_float(s: &str) -> Option<f64> {
+    match s {
+        "+inf.0" => Some(f64::INFINITY),
+        "-inf.0" => Some(f64::NEG_INFINITY),
+        "+nan.0" => Some(f64::NAN),
+        _ => None,
+    }
+}
+
+/// Prints a string, quoted in the given style.
+fn write_str(fmt: &mut Formatter, s: &str, options: PrintOptions) -> FmtResult {
+    let escape = |ch| options.escape_invisible && needs_escaping(ch);
+    if options.string_style == StringStyle::Quoted {
+        write!(fmt, "\"")?;
+        for ch in s.chars() {
+            match ch {
+                '"' => write!(fmt, "\\\"")?,
+                '\\' => write!(fmt, "\\\\")?,
+                '\n' => write!(fmt, "\\n")?,
+                '\r' => write!(fmt, "\\r")?,
+                '\t' => write!(fmt, "\\t")?,
+                '\0' => write!(fmt, "\\0")?,
+                ch if escape(ch) => write_escape(fmt, ch)?,
+                ch => write!(fmt, "{}", ch)?,
+            }
+        }
+        write!(fmt, "\"")
+    } else {
+        write!(fmt, "|")?;
+        for ch in s.chars() {
+            if escape(ch) {
+                write_escape(fmt, ch)?;
+            } else {
+                let prefix = if needs_quoting(ch) { "\\" } else { "" };
+                write!(fmt, "{}{}", prefix, ch)?;
+            }
+        }
+        write!(fmt, "|")
+    }
+}
+
+/// Prints a character as an escape sequence.
+fn write_escape(fmt: &mut Formatter, ch: char) -> FmtResult {
+    match ch {
+        '\n' => write!(fmt, "\\n"),
+        '\r' => write!(fmt, "\\r"),
+        '\t' => write!(fmt, "\\t"),
+        '\0' => write!(fmt, "\\0"),
+        ch => write!(fmt, "\\u{{{:x}}}", ch as u32),
+    }
+}
+
+/// Returns whether a character is printed as an escape sequence when `escape_invisible` is set.
+fn needs_escaping(ch: char) -> bool {
+    ch.is_control() || (ch.is_whitespace() && ch != ' ') || is_invisible(ch)
+}
+
+/// Returns whether a character is one that Unicode says should usually be invisible, such as a
+/// zero-width space, a bidirectional override or a variation selector.
+fn is_invisible(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{ad}'
+            | '\u{34f}'
+            | '\u{61c}'
+            | '\u{115f}'..='\u{1160}'
+            | '\u{17b4}'..='\u{17b5}'
+            | '\u{180b}'..='\u{180f}'
+            | '\u{200b}'..='\u{200f}'
+            | '\u{202a}'..='\u{202e}'
+            | '\u{2060}'..='\u{206f}'
+            | '\u{3164}'
+            | '\u{fe00}'..='\u{fe0f}'
+            | '\u{feff}'
+            | '\u{ffa0}'
+            | '\u{fff0}'..='\u{fff8}'
+            | '\u{1bca0}'..='\u{1bca3}'
+            | '\u{1d173}'..='\u{1d17a}'
+            | '\u{e0000}'..='\u{e0fff}'
+    )
+}
+
+/// Returns the shorthand for the list, if it is a quote form such as `(quote x)`.
+fn quote_prefix(l: &[Value]) -> Option<&'static str> {
+    let prefix = match l {
+        [Value::Sym(s), _] if s == "quote" => "'",
+        [Value::Sym(s), _] if s == "quasiquote" => "`",
+        [Value::Sym(s), _] if s == "unquote-splicing" => ",@",
+        // `,@x` would be read as `(unquote-splicing x)`.
+        [Value::Sym(s), Value::Sym(x)] if s == "unquote" && x.starts_with('@') => return None,
+        [Value::Sym(s), _] if s == "unquote" => ",",
+        _ => return None,
+    };
+    Some(prefix)
+}
+
+/// The iterator returned by `Value::children`.
+type Children<'a> = Chain<Iter<'a, Value>, option::IntoIter<&'a Value>>;
+
+/// An event in a pre-order traversal of a `Value`. The derived ordering of events is such that
+/// comparing the events of two values orders them as if `Value` derived `Ord`.
+#[derive(Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Event<'a> {
+    /// The end of a list.
+    Close,
+
+    /// The end of the elements of a dotted list, before its tail.
+    Dot,
+
+    /// The start of a `Value::List`.
+    List,
+
+    /// The start of a `Value::Bracketed`.
+    Bracketed(Bracket),
+
+    /// The start of a `Value::DottedList`.
+    DottedList,
+
+    /// A symbol.
+    Sym(&'a str),
+
+    /// A string.
+    Str(&'a str),
+
+    /// A keyword.
+    Keyword(&'a str),
+
+    /// An integer.
+    Int(i128),
+
+    /// A float.
+    Float(TotalF64),
+
+    /// A boolean.
+    Bool(bool),
+}
+
+/// A float that is ordered by the IEEE 754 total order, except that all NaNs are equal.
+#[derive(Clone, Copy, Debug)]
+struct TotalF64(f64);
+
+impl TotalF64 {
+    /// Returns the float, with every NaN replaced by the same one.
+    fn canonical(self) -> f64 {
+        if self.0.is_nan() {
+            f64::NAN
+        } else {
+            self.0
+        }
+    }
+}
+
+impl PartialEq for TotalF64 {
+    fn eq(&self, other: &TotalF64) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TotalF64 {}
+
+impl PartialOrd for TotalF64 {
+    fn partial_cmp(&self, other: &TotalF64) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalF64 {
+    fn cmp(&self, other: &TotalF64) -> Ordering {
+        self.canonical().total_cmp(&other.canonical())
+    }
+}
+
+impl Hash for TotalF64 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical().to_bits().hash(state);
+    }
+}
+
+/// The iterator returned by `Value::events`.
+struct Events<'a> {
+    next: Option<&'a Value>,
+
+    /// The lists being traversed, with their remaining elements and tail.
+    stack: Vec<(Iter<'a, Value>, Option<&'a Value>)>,
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        if let Some(value) = self.next.take() {
+            let (event, vs, tail) = match value {
+                Value::List(vs) => (Event::List, vs, None),
+                Value::Bracketed(bracket, vs) => (Event::Bracketed(*bracket), vs, None),
+                Value::DottedList(vs, tail) => (Event::DottedList, vs, Some(&**tail)),
+                Value::Sym(s) => return Some(Event::Sym(s)),
+                Value::Str(s) => return Some(Event::Str(s)),
+                Value::Keyword(name) => return Some(Event::Keyword(name)),
+                Value::Int(n) => return Some(Event::Int(*n)),
+                Value::Float(f) => return Some(Event::Float(TotalF64(*f))),
+                Value::Bool(b) => return Some(Event::Bool(*b)),
+            };
+            self.stack.push((vs.iter(), tail));
+            return Some(event);
+        }
+
+        let (vs, tail) = self.stack.last_mut()?;
+        if let Some(value) = vs.next() {
+            self.next = Some(value);
+            self.next()
+        } else if let Some(tail) = tail.take() {
+            self.next = Some(tail);
+            Some(Event::Dot)
+        } else {
+            let _ = self.stack.pop();
+            Some(Event::Close)
+        }
+    }
+}